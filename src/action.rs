@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+/// A move a player can make. Every move goes through `Multiverse::apply`,
+/// whichever frontend it comes from.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Action {
+    Check {
+        timeline: usize,
+    },
+    Call {
+        timeline: usize,
+    },
+    /// open the betting on a timeline. Bet and raise amounts are what the
+    /// player's bet on the street comes to, not the chips added.
+    Bet {
+        timeline: usize,
+        amount: i64,
+    },
+    Raise {
+        timeline: usize,
+        amount: i64,
+    },
+    /// bet everything the player has left
+    AllIn {
+        timeline: usize,
+    },
+    Fold {
+        timeline: usize,
+    },
+    /// swap the cards at these indices in the player's hand for new ones
+    Draw {
+        timeline: usize,
+        discards: Vec<usize>,
+    },
+    /// send a card to another board, raising by `raise` on the timeline it
+    /// was taken from
+    TimeTravel {
+        from: BoardCoordinate,
+        to: BoardCoordinate,
+        card: CardLocation,
+        raise: i64,
+    },
+}
+
+/// A board in the multiverse: its timeline, and its index in that timeline
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct BoardCoordinate {
+    pub timeline: usize,
+    pub board: usize,
+}
+
+/// A card on a board. `player` is `None` for the open cards.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct CardLocation {
+    pub player: Option<usize>,
+    pub index: usize,
+}
+
+/// What an action did
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Event {
+    Checked,
+    Called,
+    Bet {
+        amount: i64,
+    },
+    Raised {
+        amount: i64,
+    },
+    AllIn {
+        amount: i64,
+    },
+    Folded,
+    /// a draw, and how many new cards the player got
+    Drew {
+        count: usize,
+    },
+    /// the card ended up on this board, which is on a new timeline if it
+    /// was sent into the past
+    TimeTravelled {
+        burned: i64,
+        timeline: usize,
+        board: usize,
+    },
+}
+
+/// An action as it was applied, with the seed for any shuffles it caused, so
+/// that it can be replayed exactly
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct LoggedAction {
+    pub action: Action,
+    pub seed: u64,
+    /// who made the move
+    pub player: usize,
+}
@@ -1,14 +1,21 @@
 pub mod turn;
 
 use crate::cards::*;
+use crate::rules::GameRules;
+use serde::{Deserialize, Serialize};
 pub use turn::Turn;
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Board(pub Vec<Turn>, pub bool); // second element is whether to show the present
 
 impl Board {
-    pub fn new(deck: Vec<Card>, num_players: usize) -> Self {
-        Board(vec![Turn::first_round(deck, num_players)], false)
+    pub fn new(
+        deck: Vec<Card>,
+        stacks: &[i64],
+        dealer: usize,
+        rules: &GameRules,
+    ) -> Self {
+        Board(vec![Turn::first_round(deck, stacks, dealer, rules)], false)
     }
 
     pub fn timeline_intersect(base: &Board, turn: usize) -> Board {
@@ -18,10 +25,9 @@ impl Board {
     }
 
     pub fn is_past(&self, turn_limit: Option<usize>) -> bool {
-        if let Some(turn_limit) = turn_limit {
-            return self.0.len() > turn_limit + 1;
-        } else {
-            return false;
+        match turn_limit {
+            Some(turn_limit) => self.0.len() > turn_limit + 1,
+            None => false,
         }
     }
 
@@ -29,8 +35,46 @@ impl Board {
         &self.0[turn_limit.unwrap_or(self.0.len() - 1)]
     }
 
-    pub fn get_turn_mut(&mut self, turn_limit: Option<usize>) -> &mut Turn {
-        let index = turn_limit.unwrap_or(self.0.len() - 1);
-        &mut self.0[index]
+    /// Applies an edit to the snapshot at `turn` and every later one, so
+    /// that the rest of the board's history agrees with it. Edits that
+    /// should leave the later history alone need a new timeline instead.
+    pub fn edit_from(&mut self, turn: usize, mut edit: impl FnMut(&mut Turn)) {
+        for state in self.0.iter_mut().skip(turn) {
+            edit(state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_from_changes_later_snapshots() {
+        let mut deck = fresh_deck();
+        deck.truncate(20);
+        let mut board = Board::new(deck, &[30, 30], 0, &GameRules::default());
+        board.0.resize(3, board.0[0].clone());
+        board.edit_from(1, |turn| turn.bet_amount = 10);
+        let bets: Vec<i64> =
+            board.0.iter().map(|turn| turn.bet_amount).collect();
+        assert_eq!(bets, [2, 10, 10]);
+    }
+
+    #[test]
+    fn cards_add_up_with_time_travel_recorded() {
+        let board =
+            Board::new(fresh_deck(), &[30, 30], 0, &GameRules::default());
+        let mut turn = board.0[0].clone();
+        assert!(turn.cards_add_up());
+        let copy = turn.player_states[0].hand[0];
+        turn.open_cards.push(copy);
+        assert!(!turn.cards_add_up());
+        turn.arrivals.push(copy);
+        assert!(turn.cards_add_up());
+        let gone = turn.deck.pop().unwrap();
+        assert!(!turn.cards_add_up());
+        turn.departures.push(gone);
+        assert!(turn.cards_add_up());
     }
 }
@@ -1,41 +1,356 @@
 use crate::cards::*;
 use crate::player::PlayerState;
+use crate::rules::{GameRules, ParadoxPolicy, Street};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Turn {
     pub player_states: Vec<PlayerState>,
     pub open_cards: Vec<Card>,
     pub deck: Vec<Card>,
-    pub completed_stage: usize,
+    /// cards swapped out on a draw
+    pub discards: Vec<Card>,
+    /// cards burned before dealing a street
+    pub burned: Vec<Card>,
+    /// cards taken out of play by a paradox
+    pub annihilated: Vec<Card>,
+    /// cards that time travel brought to this board
+    pub arrivals: Vec<Card>,
+    /// cards that time travel took away from this board
+    pub departures: Vec<Card>,
+    /// index of the street being played in the variant's streets
+    pub street: usize,
     pub bet_amount: i64,
+    /// size of the biggest raise on this street
+    pub last_raise: i64,
     pub num_checks: usize,
     pub winning_hand_type: Option<HandType>,
+    /// seat of the dealer button for this hand
+    pub dealer: usize,
+    /// seat of the player whose action it is
+    pub to_act: usize,
 }
 
 impl Turn {
-    pub fn first_round(mut deck: Vec<Card>, num_players: usize) -> Self {
-        let mut player_states = vec![];
-        for _ in 1..=num_players {
-            player_states.push(PlayerState {
+    /// Deals a new hand with the button at `dealer`, and posts the antes
+    /// and blinds. Heads up, the dealer posts the small blind.
+    ///
+    /// `stacks` holds the chips each player can still bet. Nobody posts
+    /// more than that, and whoever posts their last chip is all in. Players
+    /// with no chips sit the hand out, and the button and blinds pass over
+    /// them.
+    pub fn first_round(
+        deck: Vec<Card>,
+        stacks: &[i64],
+        dealer: usize,
+        rules: &GameRules,
+    ) -> Self {
+        let player_states = stacks
+            .iter()
+            .map(|&stack| PlayerState {
+                hand: vec![],
+                up_cards: vec![],
                 bet: vec![],
-                folded: false,
-                hand: vec![
-                    deck.pop().unwrap(),
-                    deck.pop().unwrap(),
-                    // deck.pop().unwrap(), // remove these for hold-em
-                    // deck.pop().unwrap(),
-                ],
-            });
-        }
-        let open = vec![];
-        Self {
+                folded: stack <= 0,
+                all_in: false,
+                round_bet: 0,
+            })
+            .collect();
+        let mut turn = Self {
             deck,
-            open_cards: open,
+            discards: vec![],
+            burned: vec![],
+            annihilated: vec![],
+            arrivals: vec![],
+            departures: vec![],
+            open_cards: vec![],
             player_states,
-            completed_stage: 0,
-            bet_amount: 0,
+            street: 0,
+            bet_amount: rules.big_blind,
+            last_raise: rules.big_blind,
             num_checks: 0,
             winning_hand_type: None,
+            dealer,
+            to_act: dealer,
+        };
+        // time travel can leave the deck short, and then the last players
+        // get fewer cards
+        for seat in 0..stacks.len() {
+            if !turn.player_states[seat].folded {
+                let hand = turn.deal(rules.hole_cards);
+                turn.player_states[seat].hand = hand;
+            }
         }
+        let player_states = &mut turn.player_states;
+        let mut left = stacks.to_vec();
+        let mut post = |state: &mut PlayerState, seat: usize, amount: i64| {
+            let posted = amount.min(left[seat]);
+            left[seat] -= posted;
+            if posted > 0 {
+                state.bet.push(posted);
+            }
+            if left[seat] == 0 {
+                state.all_in = true;
+            }
+            posted
+        };
+        for (seat, state) in player_states.iter_mut().enumerate() {
+            if !state.folded {
+                post(state, seat, rules.ante);
+            }
+        }
+        let num_players = stacks.len();
+        // `seat`, or the first seat after it that is dealt in
+        let dealt_in = |seat: usize| {
+            (seat..seat + num_players)
+                .map(|i| i % num_players)
+                .find(|&i| !player_states[i].folded)
+                .unwrap_or(seat % num_players)
+        };
+        let heads_up =
+            player_states.iter().filter(|state| !state.folded).count() == 2;
+        let dealer = dealt_in(dealer);
+        let small_blind = if heads_up {
+            dealer
+        } else {
+            dealt_in(dealer + 1)
+        };
+        let big_blind = dealt_in(small_blind + 1);
+        for (seat, blind) in [
+            (small_blind, rules.small_blind),
+            (big_blind, rules.big_blind),
+        ] {
+            let state = &mut player_states[seat];
+            state.round_bet = post(state, seat, blind);
+        }
+        turn.dealer = dealer;
+        turn.to_act = turn.next_to_act(big_blind);
+        turn.deal_streets(rules);
+        turn
+    }
+
+    /// Every card on the board, wherever it is
+    pub fn cards(&self) -> impl Iterator<Item = &Card> {
+        self.cards_in_play()
+            .chain(&self.deck)
+            .chain(&self.discards)
+            .chain(&self.burned)
+            .chain(&self.annihilated)
+    }
+
+    /// The cards in players' hands and on the board
+    pub fn cards_in_play(&self) -> impl Iterator<Item = &Card> {
+        self.player_states
+            .iter()
+            .flat_map(|p| p.hand.iter().chain(&p.up_cards))
+            .chain(&self.open_cards)
+    }
+
+    /// Whether the cards on the board make up exactly one deck, apart from
+    /// the ones time travel is recorded to have brought or taken away
+    pub fn cards_add_up(&self) -> bool {
+        let mut expected = fresh_deck();
+        expected.extend(&self.arrivals);
+        let mut cards: Vec<Card> = self.cards().copied().collect();
+        cards.extend(&self.departures);
+        if cards.len() != expected.len() {
+            return false;
+        }
+        expected.iter().all(|card| {
+            match cards.iter().position(|other| other.same_face(card)) {
+                Some(i) => {
+                    cards.swap_remove(i);
+                    true
+                }
+                None => false,
+            }
+        })
+    }
+
+    /// Whether the hand has been played out
+    pub fn is_finished(&self) -> bool {
+        self.winning_hand_type.is_some()
+    }
+
+    /// Moves past the betting or drawing round that was just completed,
+    /// with the action on the first player after the button
+    pub fn next_round(&mut self) {
+        self.bet_amount = 0;
+        self.last_raise = 0;
+        self.num_checks = 0;
+        for player in self.player_states.iter_mut() {
+            player.round_bet = 0;
+        }
+        self.to_act = self.next_to_act(self.dealer);
+        self.street += 1;
+    }
+
+    /// Cards that are in play more than once, which only time travel can
+    /// bring about. Each one is listed once for every extra copy.
+    pub fn paradoxes(&self) -> Vec<Card> {
+        let mut seen: Vec<Card> = vec![];
+        let mut paradoxes = vec![];
+        for card in self.cards_in_play() {
+            if seen.iter().any(|other| other.same_face(card)) {
+                paradoxes.push(*card);
+            } else {
+                seen.push(*card);
+            }
+        }
+        paradoxes
+    }
+
+    /// Takes copies of cards that are in play more than once out of play,
+    /// as the policy says. Under `Forbid` one copy stays, preferring one
+    /// that was dealt on this board.
+    pub fn resolve_paradoxes(&mut self, policy: ParadoxPolicy) {
+        if policy == ParadoxPolicy::Allow {
+            return;
+        }
+        let mut faces: Vec<Card> = vec![];
+        for card in self.paradoxes() {
+            if !faces.iter().any(|face| face.same_face(&card)) {
+                faces.push(card);
+            }
+        }
+        let Self {
+            player_states,
+            open_cards,
+            annihilated,
+            ..
+        } = self;
+        let mut piles: Vec<&mut Vec<Card>> = player_states
+            .iter_mut()
+            .flat_map(|p| [&mut p.hand, &mut p.up_cards])
+            .chain([open_cards])
+            .collect();
+        for face in faces {
+            let native = piles
+                .iter()
+                .flat_map(|pile| pile.iter())
+                .any(|card| card.same_face(&face) && card.origin.is_none());
+            let mut kept = policy == ParadoxPolicy::Annihilate;
+            for pile in piles.iter_mut() {
+                pile.retain(|card| {
+                    let copy = card.same_face(&face);
+                    let keep =
+                        !copy || (!kept && (card.origin.is_none() || !native));
+                    if !keep {
+                        annihilated.push(*card);
+                    } else if copy {
+                        kept = true;
+                    }
+                    keep
+                });
+            }
+        }
+    }
+
+    /// Deals the streets from the current one up to the next one where the
+    /// players have something to do. Betting is passed over once nobody is
+    /// left to bet against, so the rest of the hand is dealt out, but the
+    /// players still in the hand all draw.
+    pub fn deal_streets(&mut self, rules: &GameRules) {
+        let streets = rules.variant.streets();
+        while let Some(&street) = streets.get(self.street) {
+            let dealt =
+                matches!(street, Street::Community(_) | Street::Deal { .. });
+            if dealt && rules.burn_cards && self.street > 0 {
+                let card = self.deal(1);
+                self.burned.extend(card);
+            }
+            let players_to_act = self
+                .player_states
+                .iter()
+                .filter(|p| !p.folded && !p.all_in)
+                .count();
+            let players_in_hand =
+                self.player_states.iter().filter(|p| !p.folded).count();
+            match street {
+                Street::Community(n) => {
+                    let cards = self.deal(n);
+                    self.open_cards.extend(cards);
+                }
+                Street::Deal { down, up } => {
+                    for player in 0..self.player_states.len() {
+                        if self.player_states[player].folded {
+                            continue;
+                        }
+                        let down = self.deal(down);
+                        let up = self.deal(up);
+                        let state = &mut self.player_states[player];
+                        state.hand.extend(down);
+                        state.up_cards.extend(up);
+                    }
+                }
+                // players who are all in still get to draw
+                Street::Draw => {
+                    if players_in_hand > 1 {
+                        self.to_act = self.next_to_draw(self.dealer);
+                        return;
+                    }
+                }
+                Street::Betting => {
+                    if players_to_act > 1 {
+                        return;
+                    }
+                }
+            }
+            self.street += 1;
+        }
+    }
+
+    /// Takes up to `n` cards off the deck
+    pub fn deal(&mut self, n: usize) -> Vec<Card> {
+        let n = n.min(self.deck.len());
+        self.deck.split_off(self.deck.len() - n)
+    }
+
+    /// The next seat after `seat` that is still in the hand and has chips
+    /// to bet, or `seat` itself if there is none
+    pub fn next_to_act(&self, seat: usize) -> usize {
+        let n = self.player_states.len();
+        (1..=n)
+            .map(|i| (seat + i) % n)
+            .find(|&i| {
+                let state = &self.player_states[i];
+                !state.folded && !state.all_in
+            })
+            .unwrap_or(seat)
+    }
+
+    /// The next seat after `seat` that is still in the hand, all in or not,
+    /// or `seat` itself if there is none
+    pub fn next_to_draw(&self, seat: usize) -> usize {
+        let n = self.player_states.len();
+        (1..=n)
+            .map(|i| (seat + i) % n)
+            .find(|&i| !self.player_states[i].folded)
+            .unwrap_or(seat)
+    }
+
+    /// Whether the players are swapping cards on the current street
+    pub fn is_drawing(&self, rules: &GameRules) -> bool {
+        rules.variant.streets().get(self.street) == Some(&Street::Draw)
+    }
+
+    /// A player's hand, or the open cards for `None`
+    pub fn pile(&self, player: Option<usize>) -> &[Card] {
+        match player {
+            Some(player) => &self.player_states[player].hand,
+            None => &self.open_cards,
+        }
+    }
+
+    pub fn pile_mut(&mut self, player: Option<usize>) -> &mut Vec<Card> {
+        match player {
+            Some(player) => &mut self.player_states[player].hand,
+            None => &mut self.open_cards,
+        }
+    }
+
+    /// Chips `player` has to put in to match the current bet
+    pub fn to_call(&self, player: usize) -> i64 {
+        self.bet_amount - self.player_states[player].round_bet
     }
 }
@@ -2,6 +2,7 @@ use crate::board::*;
 use crate::game::*;
 use crate::hand::*;
 use crate::player::*;
+use crate::rules::*;
 use yew::prelude::*;
 
 const CARD_LAYOUTS: [&str; 5] = [
@@ -27,6 +28,11 @@ pub struct BoardDisplayProps {
     pub turn_limit: Option<usize>,
     pub coordinates: (usize, usize),
     pub active_player: usize,
+    /// chips the active player has left to bet
+    pub available: i64,
+    /// smallest and largest legal bet or raise for the active player
+    pub bet_range: (i64, i64),
+    pub variant: Variant,
     pub players: Vec<Player>,
     #[prop_or_default]
     pub ondragstart: Callback<(DragEvent, usize, usize)>, // player, card
@@ -38,39 +44,28 @@ pub struct BoardDisplayProps {
 
 #[function_component]
 pub fn BoardDisplay(props: &BoardDisplayProps) -> Html {
-    if let Some(turn_limit) = props.turn_limit {
-        let turn = props.board.get_turn(Some(turn_limit));
-        if turn_limit + 1 == props.board.0.len() {
-            // only continue to fold if not at the present, and also not when the game is over
-            if turn.player_states[props.active_player].folded
-                || turn.completed_stage >= 4
-            {
-                props.onbuttonclick.emit(ButtonType::DoNothing);
-            }
-        }
-    }
     let turn = props.board.get_turn(props.turn_limit);
+    let to_call = turn.to_call(props.active_player);
     let in_future = props.board.is_past(props.turn_limit);
-    let is_in_future = if in_future
-        || props.board.get_turn(props.turn_limit).completed_stage >= 4
-    {
-        "disabled-board"
-    } else {
-        ""
-    };
+    let is_in_future =
+        if in_future || props.board.get_turn(props.turn_limit).is_finished() {
+            "disabled-board"
+        } else {
+            ""
+        };
     let left = props.coordinates.0 * 550 + 25;
     let top = props.coordinates.1 * 362 + 25;
     let style = format!("left: {left}px; top: {top}px");
     let active_state = &props.board.get_turn(props.turn_limit);
-    let active_hand = if active_state.player_states[props.active_player].folded
-    {
-        vec![]
-    } else {
-        active_state.player_states[props.active_player].hand.clone()
-    };
+    let (active_hand, active_up_cards) =
+        if active_state.player_states[props.active_player].folded {
+            (vec![], vec![])
+        } else {
+            let state = &active_state.player_states[props.active_player];
+            (state.hand.clone(), state.up_cards.clone())
+        };
 
-    let card_layout_indices =
-        CARD_LAYOUT_INDICES[props.players.len() - 1].clone();
+    let card_layout_indices = CARD_LAYOUT_INDICES[props.players.len() - 1];
 
     let mut enemy_hands = vec![];
     for i in 1..active_state.player_states.len() {
@@ -80,21 +75,21 @@ pub fn BoardDisplay(props: &BoardDisplayProps) -> Html {
         let playerstatedisplay = {
             PlayerStateDisplay {
                 bank: player.chips,
-                name: player.name.to_string(),
+                name: seat_name(player, player_number, turn),
                 betting: props.board.get_turn(props.turn_limit).player_states
                     [player_number]
                     .commitment(),
             }
         };
-        let hand = if active_state.player_states[player_number].folded {
-            vec![]
+        let (hand, up_cards) = if playerstate.folded {
+            (vec![], vec![])
         } else {
-            active_state.player_states[player_number].hand.clone()
+            (playerstate.hand.clone(), playerstate.up_cards.clone())
         };
         enemy_hands.push({
             html! {
-                <Hand {hand}
-                      visible={turn.completed_stage >= 4 && !playerstate.folded}
+                <Hand {hand} {up_cards}
+                      visible={turn.is_finished() && !playerstate.folded}
                       playerstate={playerstatedisplay}
                       style={CARD_LAYOUTS[card_layout_indices[i - 1]]}
                   />
@@ -120,7 +115,7 @@ pub fn BoardDisplay(props: &BoardDisplayProps) -> Html {
         let player = &props.players[props.active_player];
         PlayerStateDisplay {
             bank: player.chips,
-            name: player.name.to_string(),
+            name: seat_name(player, props.active_player, turn),
             betting: props.board.get_turn(props.turn_limit).player_states
                 [props.active_player]
                 .commitment(),
@@ -139,31 +134,66 @@ pub fn BoardDisplay(props: &BoardDisplayProps) -> Html {
         bank: props.board.get_turn(props.turn_limit).bet_amount,
     };
 
+    let (min_bet, max_bet) = props.bet_range;
+    let bet_range = if min_bet == max_bet {
+        format!("{}⏲", min_bet)
+    } else {
+        format!("{}–{}⏲", min_bet, max_bet)
+    };
+    let drawing =
+        props.variant.streets().get(turn.street) == Some(&Street::Draw);
     let buttons = if in_future
         || props.turn_limit.is_none()
-        || turn.completed_stage >= 4
+        || turn.is_finished()
+        || turn.to_act != props.active_player
     {
         html! {}
+    } else if drawing {
+        html! {
+            <div class="actions">
+                <button class="do-button" onclick={props.onbuttonclick.reform(|_| ButtonType::Draw)}>{"Draw"}</button>
+                <button class="do-button" onclick={props.onbuttonclick.reform(|_| ButtonType::Fold)}>{"Fold"}</button>
+            </div>
+        }
     } else {
         html! {
             <div class="actions">
-                <button class="do-button" onclick={props.onbuttonclick.reform(|_| ButtonType::CallOrCheck)}>
-                    {if turn.bet_amount == 0 { "Check" } else { "Call" }}
-                </button>
-                <button class="do-button" onclick={props.onbuttonclick.reform(|_| ButtonType::RaiseOrBet)}>
-                    {if turn.bet_amount == 0 { "Bet" } else { "Raise" }}
-                </button>
+                if props.available < to_call {
+                    <button class="do-button" onclick={props.onbuttonclick.reform(|_| ButtonType::AllIn)}>
+                        {"All in"}
+                    </button>
+                } else {
+                    <button class="do-button" onclick={props.onbuttonclick.reform(|_| ButtonType::CallOrCheck)}>
+                        {if to_call == 0 { "Check" } else { "Call" }}
+                    </button>
+                }
+                if min_bet <= max_bet {
+                    <button class="do-button" onclick={props.onbuttonclick.reform(|_| ButtonType::RaiseOrBet)}>
+                        {format!("{} {}", if turn.bet_amount == 0 { "Bet" } else { "Raise" }, bet_range)}
+                    </button>
+                }
                 <button class="do-button" onclick={props.onbuttonclick.reform(|_| ButtonType::Fold)}>{"Fold"}</button>
             </div>
         }
     };
 
-    let winning_type_display = if let Some(winning_type) = turn.winning_hand_type {
-        html!{
+    let winning_type_display = if let Some(winning_type) =
+        turn.winning_hand_type
+    {
+        html! {
             <div class="winning-hand-display">{format!("{}!", winning_type)}</div>
         }
     } else {
-        html!{}
+        html! {}
+    };
+
+    // the deck can run out on a full table
+    let deck_display = if let Some(&top) = active_state.deck.first() {
+        html! {
+            <Hand hand={vec![top]} visible={false} style="top: 150px; left: 150px; transform: none" />
+        }
+    } else {
+        html! {}
     };
 
     let ondragover = {
@@ -181,13 +211,14 @@ pub fn BoardDisplay(props: &BoardDisplayProps) -> Html {
         <div class={format!("table absolute {}", is_in_future)}
             {style} {ondragover} ondrop={props.ondrop.clone()}>
             <Hand hand={active_hand}
+                up_cards={active_up_cards}
                 visible={true}
                 style="bottom: 25px; left: 250px"
                 draggable={!in_future}
                 {playerstate}
                 ondragstart={ondragstart_player} />
             {for enemy_hands}
-            <Hand hand={vec![active_state.deck[0]]} visible={false} style="top: 150px; left: 150px; transform: none" />
+            {deck_display}
             <Hand hand={active_state.open_cards.clone()} playerstate={potinfo}
                 visible={true}
                 style="top: 150px; left: 200px; transform: none; text-align: left"
@@ -200,6 +231,15 @@ pub fn BoardDisplay(props: &BoardDisplayProps) -> Html {
     }
 }
 
+/// A player's name, marked if they have the dealer button
+fn seat_name(player: &Player, seat: usize, turn: &Turn) -> String {
+    if seat == turn.dealer {
+        format!("{} Ⓓ", player.name)
+    } else {
+        player.name.to_string()
+    }
+}
+
 fn clock(label: &str, onclick: Callback<MouseEvent>) -> Html {
     let ticks = (0..12_i8).map(|x| {
         html! {
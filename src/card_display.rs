@@ -0,0 +1,56 @@
+use crate::cards::*;
+use yew::prelude::*;
+
+#[derive(PartialEq, Properties)]
+pub struct CardDisplayProps {
+    #[prop_or_default]
+    pub onclick: Callback<MouseEvent>,
+    #[prop_or_default]
+    pub style: String,
+    pub card: Card,
+    #[prop_or(true)]
+    pub visible: bool,
+    #[prop_or(false)]
+    pub draggable: bool,
+    #[prop_or_default]
+    pub ondragstart: Callback<DragEvent>,
+}
+
+#[function_component]
+pub fn CardDisplay(props: &CardDisplayProps) -> Html {
+    let card = props.card;
+    let style = props.style.to_string();
+    let onclick = props.onclick.clone();
+    use Suite::*;
+    let color = match card.suite {
+        Hearts | Diamonds => "red-card",
+        Clubs | Spades => "black-card",
+    };
+    let (face, visibility) = if !props.visible {
+        ("".to_string(), "card-back")
+    } else {
+        (format!("{}{}", card.rank.repr(), card.suite.repr()), "")
+    };
+    // cards brought by time travel are marked, face up or down
+    let (foreign, title) = match card.origin {
+        Some(origin) => (
+            "foreign-card",
+            format!(
+                "From timeline {}, turn {}",
+                origin.timeline + 1,
+                origin.time + 1
+            ),
+        ),
+        None => ("", String::new()),
+    };
+    html! {
+        <div class={format!("card {} {} {}", color, visibility, foreign)}
+            {style}
+            {title}
+            {onclick}
+            draggable={format!("{}", props.draggable)}
+            ondragstart={props.ondragstart.clone()}>
+            {face}
+        </div>
+    }
+}
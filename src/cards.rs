@@ -1,7 +1,7 @@
-use itertools::*;
-use yew::prelude::*;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 
-#[derive(PartialEq, Copy, Clone, Eq, Debug)]
+#[derive(PartialEq, Copy, Clone, Eq, Debug, Serialize, Deserialize)]
 pub enum Suite {
     Clubs,
     Hearts,
@@ -10,7 +10,20 @@ pub enum Suite {
 }
 
 impl Suite {
-    fn repr(&self) -> &'static str {
+    pub const ALL: [Suite; 4] =
+        [Suite::Clubs, Suite::Hearts, Suite::Spades, Suite::Diamonds];
+
+    fn index(&self) -> usize {
+        use Suite::*;
+        match self {
+            Clubs => 0,
+            Hearts => 1,
+            Spades => 2,
+            Diamonds => 3,
+        }
+    }
+
+    pub fn repr(&self) -> &'static str {
         use Suite::*;
         match self {
             Clubs => "♣",
@@ -22,11 +35,13 @@ impl Suite {
 }
 
 /// Require 1 <= internal_value <= 13
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[derive(
+    PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize,
+)]
 pub struct Rank(u8);
 
 impl Rank {
-    fn repr(&self) -> &'static str {
+    pub fn repr(&self) -> &'static str {
         match self.0 {
             1 => "A",
             2 => "2",
@@ -44,32 +59,61 @@ impl Rank {
             _ => "?",
         }
     }
+
+    /// Value used when comparing ranks, with aces high
+    pub fn value(&self) -> u8 {
+        if self.0 == 1 {
+            14
+        } else {
+            self.0
+        }
+    }
 }
 
-pub fn calculate_winners<T>(hands: &Vec<(T, Vec<Card>)>) -> (Vec<T>, HandType)
+/// The players holding the best of some scored hands, and what they hold
+pub fn calculate_winners<T>(hands: &[(T, HandValue)]) -> (Vec<T>, HandValue)
 where
     T: Sized + Copy + Clone,
 {
-    let hands: Vec<(T, HandType)> = hands
-        .iter()
-        .map(|(a, cards)| (a.clone(), type_of_hand(&cards)))
-        .collect();
-    let winning_type = hands.iter().map(|a| a.1).max().unwrap();
+    let winning_value = hands.iter().map(|a| a.1).max().unwrap();
     let winners = hands
         .iter()
-        .filter(|a| a.1 == winning_type)
+        .filter(|a| a.1 == winning_value)
         .map(|a| a.0)
         .collect();
-    (winners, winning_type)
+    (winners, winning_value)
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Card {
-    suite: Suite,
-    rank: Rank,
+    pub suite: Suite,
+    pub rank: Rank,
+    /// where the card was dealt, if time travel has taken it to another
+    /// board
+    pub origin: Option<Provenance>,
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug)]
+impl Card {
+    /// Whether two cards have the same suite and rank, wherever they came
+    /// from
+    pub fn same_face(&self, other: &Card) -> bool {
+        self.suite == other.suite && self.rank == other.rank
+    }
+}
+
+/// The board a card was dealt on, and who held it there
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Provenance {
+    pub timeline: usize,
+    /// absolute time of the board
+    pub time: usize,
+    /// the player whose hand it was in, or `None` for the open cards
+    pub owner: Option<usize>,
+}
+
+#[derive(
+    PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug, Serialize, Deserialize,
+)]
 pub enum HandType {
     NoPair,
     OnePair,
@@ -83,6 +127,14 @@ pub enum HandType {
     FiveOfAKind,
 }
 
+/// Full strength of a hand. Hands compare by category first, then by the
+/// tiebreak ranks (aces high), most significant first.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug)]
+pub struct HandValue {
+    pub hand_type: HandType,
+    pub tiebreak: [u8; 5],
+}
+
 impl std::fmt::Display for HandType {
     fn fmt(
         &self,
@@ -107,142 +159,302 @@ impl std::fmt::Display for HandType {
     }
 }
 
-pub fn type_of_hand(hand: &Vec<Card>) -> HandType {
+/// Scores the best five card hand that can be made from `hand`.
+///
+/// Works from per-rank and per-suite counts, so it takes a single pass over
+/// the cards however many there are. Duplicate cards (which time travel can
+/// produce) are counted like any other, so five of a kind is possible.
+pub fn type_of_hand(hand: &[Card]) -> HandValue {
     use HandType::*;
-    let mut best_hand = NoPair;
-    for hand in hand.iter().combinations(5) {
-        best_hand =
-            type_of_hand_5(&hand.into_iter().cloned().collect()).max(best_hand)
+    let mut counts = [0_u8; 15];
+    let mut suited = [[0_u8; 15]; 4];
+    for card in hand {
+        let value = card.rank.value() as usize;
+        counts[value] += 1;
+        suited[card.suite.index()][value] += 1;
     }
-    best_hand
-}
+    let singles = rank_mask(&counts, 1);
+    let pairs = rank_mask(&counts, 2);
+    let trips = rank_mask(&counts, 3);
 
-pub fn type_of_hand_5(hand: &Vec<Card>) -> HandType {
-    use HandType::*;
-    let ranks = split_by_rank(hand);
-    let suites = split_by_suite(hand);
-    let sequences = split_by_sequence(hand);
-    if ranks[0] >= 5 {
-        return FiveOfAKind;
+    if let Some(rank) = values(rank_mask(&counts, 5)).next() {
+        return hand_value(FiveOfAKind, [rank]);
     }
-    if sequences[0] >= 5 && suites[0] >= 5 {
-        return StraightFlush;
+    let straight_flush = suited
+        .iter()
+        .filter_map(|suite| straight_high(rank_mask(suite, 1)))
+        .max();
+    if let Some(high) = straight_flush {
+        return hand_value(StraightFlush, [high]);
     }
-    if ranks[0] >= 4 {
-        return FourOfAKind;
+    if let Some(quad) = values(rank_mask(&counts, 4)).next() {
+        let kicker = values(singles & !bit(quad)).take(1);
+        return hand_value(FourOfAKind, Some(quad).into_iter().chain(kicker));
     }
-    if ranks[0] >= 3 && ranks[1] >= 2 {
-        return FullHouse;
+    if let Some(trip) = values(trips).next() {
+        if let Some(pair) = values(pairs & !bit(trip)).next() {
+            return hand_value(FullHouse, [trip, pair]);
+        }
     }
-    if suites[0] >= 5 {
-        return Flush;
+    let flush = suited
+        .iter()
+        .filter(|suite| suite.iter().sum::<u8>() >= 5)
+        .map(|suite| hand_value(Flush, top_cards(suite)))
+        .max();
+    if let Some(flush) = flush {
+        return flush;
     }
-    if sequences[0] >= 5 {
-        return Straight;
+    if let Some(high) = straight_high(singles) {
+        return hand_value(Straight, [high]);
     }
-    if ranks[0] >= 3 {
-        return ThreeOfAKind;
+    if let Some(trip) = values(trips).next() {
+        let kickers = values(singles & !bit(trip)).take(2);
+        return hand_value(ThreeOfAKind, Some(trip).into_iter().chain(kickers));
     }
-    if ranks[0] >= 2 && ranks[1] >= 2 {
-        return TwoPairs;
+    let mut paired = values(pairs);
+    match (paired.next(), paired.next()) {
+        (Some(high), Some(low)) => {
+            let kicker = values(singles & !bit(high) & !bit(low)).take(1);
+            hand_value(TwoPairs, [high, low].into_iter().chain(kicker))
+        }
+        (Some(pair), None) => {
+            let kickers = values(singles & !bit(pair)).take(3);
+            hand_value(OnePair, Some(pair).into_iter().chain(kickers))
+        }
+        _ => hand_value(NoPair, values(singles).take(5)),
+    }
+}
+
+/// Scores the best Omaha hand: exactly two of the hole cards with exactly
+/// three from the board. If time travel has left fewer than that, all of
+/// them are used.
+pub fn omaha_hand(hole: &[Card], board: &[Card]) -> HandValue {
+    hole.iter()
+        .combinations(hole.len().min(2))
+        .cartesian_product(board.iter().combinations(board.len().min(3)))
+        .map(|(hole, board)| {
+            let cards: Vec<Card> =
+                hole.into_iter().chain(board).copied().collect();
+            type_of_hand(&cards)
+        })
+        .max()
+        .unwrap()
+}
+
+fn hand_value(
+    hand_type: HandType,
+    ranks: impl IntoIterator<Item = u8>,
+) -> HandValue {
+    let mut tiebreak = [0; 5];
+    for (slot, rank) in tiebreak.iter_mut().zip(ranks) {
+        *slot = rank;
     }
-    if ranks[0] >= 2 {
-        return OnePair;
+    HandValue {
+        hand_type,
+        tiebreak,
     }
+}
 
-    NoPair
+fn bit(value: u8) -> u16 {
+    1 << value
 }
 
-pub fn split_by_sequence(hand: &Vec<Card>) -> Vec<usize> {
-    let mut hand: Vec<u8> =
-        hand.clone().into_iter().map(|x| x.rank.0).collect();
-    hand.sort();
+/// Bitmask of the rank values that appear at least `min` times
+fn rank_mask(counts: &[u8; 15], min: u8) -> u16 {
+    (2..=14)
+        .filter(|&value| counts[value] >= min)
+        .fold(0, |mask, value| mask | bit(value as u8))
+}
 
-    let mut out = vec![];
-    let mut last = 13;
-    for i in hand.iter() {
-        if i == &(last + 1) {
-            *(out.last_mut().unwrap()) += 1;
-        } else {
-            out.push(1);
-        }
-        last = *i;
-    }
-    out.sort();
-    out
+/// Rank values in a mask, highest first
+fn values(mask: u16) -> impl Iterator<Item = u8> {
+    (2..=14).rev().filter(move |&value| mask & bit(value) != 0)
 }
 
-pub fn split_by_suite(hand: &Vec<Card>) -> Vec<usize> {
-    use Suite::*;
-    let mut out: Vec<_> = [Hearts, Clubs, Spades, Diamonds]
-        .iter()
-        .map(|suite| hand.iter().filter(|card| &card.suite == suite).count())
-        .collect();
-    out.sort();
-    out.reverse();
-    out
+/// The five highest cards, counting duplicates
+fn top_cards(counts: &[u8; 15]) -> impl Iterator<Item = u8> + '_ {
+    (2..=14)
+        .rev()
+        .flat_map(|value| {
+            std::iter::repeat_n(value, counts[value as usize] as usize)
+        })
+        .take(5)
 }
 
-pub fn split_by_rank(hand: &Vec<Card>) -> Vec<usize> {
-    let mut out: Vec<_> = (1..13)
-        .map(|rank| hand.iter().filter(|card| card.rank == Rank(rank)).count())
-        .collect();
-    out.sort();
-    out.reverse();
-    out
+/// Value of the top card of the best five card run in a rank mask, if there
+/// is one. Aces count both high and low, so the wheel (A-2-3-4-5) is a five
+/// high straight.
+fn straight_high(mask: u16) -> Option<u8> {
+    let mask = mask | (mask >> 14 & 1) << 1;
+    let runs = mask & mask >> 1 & mask >> 2 & mask >> 3 & mask >> 4;
+    if runs == 0 {
+        None
+    } else {
+        Some((15 - runs.leading_zeros()) as u8 + 4)
+    }
 }
 
 pub fn fresh_deck() -> Vec<Card> {
-    use Suite::*;
     let mut deck = vec![];
-    for suite in vec![Clubs, Hearts, Spades, Diamonds].into_iter() {
+    for suite in Suite::ALL {
         for rank in 1..=13 {
             deck.push(Card {
                 suite,
                 rank: Rank(rank),
+                origin: None,
             });
         }
     }
     deck
 }
 
-#[derive(PartialEq, Properties)]
-pub struct CardDisplayProps {
-    #[prop_or_default]
-    pub onclick: Callback<MouseEvent>,
-    #[prop_or_default]
-    pub style: String,
-    pub card: Card,
-    #[prop_or(true)]
-    pub visible: bool,
-    #[prop_or(false)]
-    pub draggable: bool,
-    #[prop_or_default]
-    pub ondragstart: Callback<DragEvent>,
-}
-
-#[function_component]
-pub fn CardDisplay(props: &CardDisplayProps) -> Html {
-    let card = props.card;
-    let style = props.style.to_string();
-    let onclick = props.onclick.clone();
-    use Suite::*;
-    let color = match card.suite {
-        Hearts | Diamonds => "red-card",
-        Clubs | Spades => "black-card",
-    };
-    let (face, visibility) = if !props.visible {
-        ("".to_string(), "card-back")
-    } else {
-        (format!("{}{}", card.rank.repr(), card.suite.repr()), "")
-    };
-    html! {
-        <div class={format!("card {} {}", color, visibility)}
-            {style}
-            {onclick}
-            draggable={format!("{}", props.draggable)}
-            ondragstart={props.ondragstart.clone()}>
-            {face}
-        </div>
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use HandType::*;
+
+    /// Cards from short names like "Ah Td 2c"
+    fn cards(hand: &str) -> Vec<Card> {
+        hand.split_whitespace()
+            .map(|name| {
+                let (rank, suite) = name.split_at(1);
+                Card {
+                    suite: match suite {
+                        "c" => Suite::Clubs,
+                        "h" => Suite::Hearts,
+                        "s" => Suite::Spades,
+                        _ => Suite::Diamonds,
+                    },
+                    rank: Rank("A23456789TJQK".find(rank).unwrap() as u8 + 1),
+                    origin: None,
+                }
+            })
+            .collect()
+    }
+
+    fn value(hand: &str) -> HandValue {
+        type_of_hand(&cards(hand))
+    }
+
+    #[test]
+    fn categories() {
+        let hands = [
+            ("2h 5d 9c Js Kh 3c 7d", NoPair),
+            ("2h 2d 9c Js Kh 3c 7d", OnePair),
+            ("2h 2d 9c 9s Kh 3c 7d", TwoPairs),
+            ("2h 2d 2c 9s Kh 3c 7d", ThreeOfAKind),
+            ("5h 6d 7c 8s 9h Kc 2d", Straight),
+            ("2h 5h 9h Jh Kh 3c 7d", Flush),
+            ("2h 2d 2c 9s 9h 3c 7d", FullHouse),
+            ("2h 2d 2c 2s Kh 3c 7d", FourOfAKind),
+            ("5h 6h 7h 8h 9h Kc 2d", StraightFlush),
+            ("Kh Kd Kc Ks Kh", FiveOfAKind),
+        ];
+        for (hand, hand_type) in hands {
+            assert_eq!(value(hand).hand_type, hand_type, "{}", hand);
+        }
+        for pair in hands.windows(2) {
+            assert!(value(pair[0].0) < value(pair[1].0));
+        }
+    }
+
+    #[test]
+    fn kings_count() {
+        assert_eq!(value("Kh Kd 2c 5s 9h").hand_type, OnePair);
+        assert_eq!(value("Kh Kd Kc Ks 9h").hand_type, FourOfAKind);
+        assert_eq!(value("Kh Kd Kc 2s 2h").hand_type, FullHouse);
+        assert_eq!(value("9h Th Jh Qh Kh").hand_type, StraightFlush);
+        assert!(value("Kh Kd 2c 5s 9h") > value("Qh Qd Ac 5s 9h"));
+    }
+
+    #[test]
+    fn kickers() {
+        assert!(value("Ah Kd 3c 7s 9h Jd 2c") > value("Ah Qd 3c 7s 9h Jd 2c"));
+        assert!(value("Ah Ad Kc 7s 9h") > value("Ah Ad Qc 7s 9h"));
+        assert!(value("Ah Ad Kc Ks 9h Jd 2c") > value("Ah Ad Kc Ks 9h Td 2c"));
+        assert!(value("7h 7d 7c Ks 2h") > value("7h 7d 7c Qs Jh"));
+        assert!(value("7h 7d 7c 7s Ah") > value("7h 7d 7c 7s Kh"));
+        assert!(value("8h 8d 8c 2s 2h") > value("7h 7d 7c As Ah"));
+        assert!(value("Ah 9h 7h 5h 3h") > value("Ah 9h 7h 5h 2h"));
+        // the sixth and seventh cards don't play
+        assert_eq!(
+            value("Ah Ad Kc Qs Jh 3d 2c"),
+            value("Ah Ad Kc Qs Jh 4d 3c")
+        );
+        assert_eq!(value("Ah Ad Kc Qs Jh").tiebreak, [14, 13, 12, 11, 0]);
+    }
+
+    #[test]
+    fn straights() {
+        let broadway = value("Th Jd Qc Ks Ah 2d 2c");
+        assert_eq!(broadway.hand_type, Straight);
+        assert_eq!(broadway.tiebreak[0], 14);
+        let wheel = value("Ah 2d 3c 4s 5h Kd Kc");
+        assert_eq!(wheel.hand_type, Straight);
+        assert_eq!(wheel.tiebreak[0], 5);
+        assert!(value("6h 2d 3c 4s 5h Kd Kc") > wheel);
+        // no wrapping round the ace
+        assert_eq!(value("Qh Kd Ac 2s 3h 9d 9c").hand_type, OnePair);
+        let steel_wheel = value("Ah 2h 3h 4h 5h");
+        assert_eq!(steel_wheel.hand_type, StraightFlush);
+        assert_eq!(steel_wheel.tiebreak[0], 5);
+        assert_eq!(value("Th Jh Qh Kh Ah 2d 2c").tiebreak[0], 14);
+    }
+
+    #[test]
+    fn straight_and_flush_from_different_cards() {
+        // a straight to the king and a heart flush, but no straight flush
+        let hand = value("9h Th Jh Qh 2h Kd 3c");
+        assert_eq!(hand.hand_type, Flush);
+        assert_eq!(hand.tiebreak, [12, 11, 10, 9, 2]);
+        assert_eq!(value("4c 5h 6h 7h 8d 2h Kh").hand_type, Flush);
+        // the straight flush is found among more suited cards
+        assert_eq!(value("2h 5h 6h 7h 8h 9h Kd").tiebreak[0], 9);
+    }
+
+    #[test]
+    fn five_of_a_kind() {
+        let hand = value("Ah Ah Ad Ac As 2c 2d");
+        assert_eq!(hand.hand_type, FiveOfAKind);
+        assert_eq!(hand.tiebreak[0], 14);
+        assert!(value("2h 2h 2d 2c 2s") > value("Ah Kh Qh Jh Th"));
+        assert!(value("3h 3h 3d 3c 3s") > value("2h 2h 2d 2c 2s"));
+    }
+
+    #[test]
+    fn omaha_uses_two_hole_cards() {
+        // four hearts on the board are no flush with one heart in hand
+        let hole = cards("Ah Kd Qc Js");
+        let board = cards("2h 5h 8h 9h Td");
+        assert_ne!(omaha_hand(&hole, &board).hand_type, Flush);
+        assert_eq!(omaha_hand(&hole, &board).hand_type, Straight);
+        let hole = cards("Ah Kh 2c 2s");
+        assert_eq!(omaha_hand(&hole, &board).hand_type, Flush);
+    }
+
+    #[test]
+    fn winners_split_ties() {
+        let hands = [
+            (0, value("Ah Kd 3c 7s 9h Jd 2c")),
+            (1, value("As Kc 3c 7s 9h Jd 2c")),
+            (2, value("Qs Kc 3c 7s 9h Jd 2c")),
+        ];
+        let (winners, best) = calculate_winners(&hands);
+        assert_eq!(winners, vec![0, 1]);
+        assert_eq!(best.hand_type, NoPair);
+    }
+
+    /// Every five card hand from one deck, counted by category, against the
+    /// known totals
+    #[test]
+    fn all_five_card_hands() {
+        let mut counts = [0_usize; 10];
+        for hand in fresh_deck().into_iter().combinations(5) {
+            counts[type_of_hand(&hand).hand_type as usize] += 1;
+        }
+        assert_eq!(
+            counts,
+            [1302540, 1098240, 123552, 54912, 10200, 5108, 3744, 624, 40, 0]
+        );
     }
 }
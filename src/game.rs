@@ -1,20 +1,22 @@
 use crate::board::*;
 use crate::cards::*;
-use rand::prelude::SliceRandom;
-use rand::thread_rng;
+use crate::rules::GameRules;
+use rand::prelude::{Rng, SliceRandom};
+use serde::{Deserialize, Serialize};
 
 pub enum ButtonType {
     CallOrCheck,
     RaiseOrBet,
+    AllIn,
     Fold,
+    Draw,
     ToggleView,
-    DoNothing,
 }
 
 // TIMELINES
 
 // represents a single branch of the tree...
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Timeline {
     pub parent_index: usize,
     pub starting_time: usize,
@@ -22,21 +24,25 @@ pub struct Timeline {
 }
 
 impl Timeline {
-    pub fn genesis(num_players: usize) -> Self {
+    pub fn genesis(
+        stacks: &[i64],
+        rules: &GameRules,
+        rng: &mut impl Rng,
+    ) -> Self {
         let mut deck = fresh_deck();
-        deck.shuffle(&mut thread_rng());
+        deck.shuffle(rng);
         Self {
             parent_index: 0,
             starting_time: 0,
-            boards: vec![Board::new(deck, num_players)],
+            boards: vec![Board::new(deck, stacks, 0, rules)],
         }
     }
 
     pub fn current_board(&self) -> &Board {
-        return self.boards.last().unwrap();
+        self.boards.last().unwrap()
     }
 
     pub fn current_board_mut(&mut self) -> &mut Board {
-        return self.boards.last_mut().unwrap();
+        self.boards.last_mut().unwrap()
     }
 }
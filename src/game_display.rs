@@ -42,77 +42,83 @@ pub fn GameDisplay(props: &GameDisplayProps) -> Html {
                         let i: usize =
                             dt.get_data("card").unwrap().parse().unwrap();
 
-                        let num_burn = (t_from as i64 - t as i64).abs()
-                            + (timeline_from as i64 - timeline_num as i64)
-                                .abs()
-                            + if player < game.players.len() { 0 } else { 4 };
-                        let turn_limit = game.get_turn();
-                        if (timeline_from != timeline_num || t_from != t) &&
-                         gloo_dialogs::confirm(&format!("Time travel? You will burn {}⏲ and have to raise in your current timeline", num_burn)) && game.try_raise_or_bet(timeline_from) {
-                            let initiating_player = game.get_active_player();
-                            game.players[initiating_player].chips -= num_burn;
-                            let card = {
-                                let from_turn = game.timelines[timeline_from].boards[t_from]
-                                .get_turn_mut(
-                                    Some(turn_limit).and_then(|x| Some(x + 1)),
-                                );
-                                if player < game.players.len() {
-                                    from_turn.player_states[player].hand.remove(i)
-                                } else {
-                                    from_turn.open_cards.remove(i)
-                                }
-                            };
-                            let (timeline_num, t) =
-                                if game.timelines[timeline_num].boards[t]
-                                    .is_past(Some(turn_limit))
-                                {
-                                    game.spawn_timeline(timeline_num, t)
-                                } else {
-                                    (timeline_num, t)
+                        let from = BoardCoordinate {
+                            timeline: timeline_from,
+                            board: t_from,
+                        };
+                        let to = BoardCoordinate {
+                            timeline: timeline_num,
+                            board: t,
+                        };
+                        let card = CardLocation {
+                            player: (player < game.players.len())
+                                .then_some(player),
+                            index: i,
+                        };
+                        if from == to {
+                            return;
+                        }
+                        let Some(num_burn) =
+                            report(game.time_travel_cost(from, to, card))
+                        else {
+                            return;
+                        };
+                        if gloo_dialogs::confirm(&format!("Time travel? You will burn {}⏲ and have to raise in your current timeline", num_burn)) {
+                            if let Some(raise) = prompt_bet(&game, timeline_from) {
+                                let action = Action::TimeTravel {
+                                    from,
+                                    to,
+                                    card,
+                                    raise,
                                 };
-                            let to_turn = game.timelines[timeline_num].boards[t].get_turn_mut(Some(turn_limit));
-                            if player < game.players.len() {
-                                to_turn.player_states[player].hand.push(card);
-                            } else {
-                                to_turn.open_cards.push(card);
+                                if report(game.apply(action)).is_some() {
+                                    ongameupdate.emit(game);
+                                }
                             }
-                            ongameupdate.emit(game);
                         }
                     }
                 };
                 let onbuttonclick = {
                     let game = props.game.clone();
-                    let zeroed = game.timelines[timeline_num].boards[t]
-                        .get_turn(Some(game.get_turn()))
-                        .bet_amount
-                        == 0;
+                    let turn = game.timelines[timeline_num].boards[t]
+                        .get_turn(Some(game.get_turn()));
+                    let zeroed = turn.bet_amount == 0;
+                    let checked = turn.to_call(game.get_active_player()) == 0;
                     let ongamechange = props.ongameupdate.clone();
                     move |b: ButtonType| {
                         let mut game = game.clone();
                         use ButtonType::*;
-                        match b {
-                            CallOrCheck => {
-                                if zeroed {
-                                    game.try_check(timeline_num);
-                                } else {
-                                    game.try_call(timeline_num);
-                                }
-                            }
+                        let timeline = timeline_num;
+                        let action = match b {
+                            CallOrCheck => Some(if checked {
+                                Action::Check { timeline }
+                            } else {
+                                Action::Call { timeline }
+                            }),
                             RaiseOrBet => {
-                                if zeroed {
-                                    game.try_initial_bet(timeline_num);
-                                } else {
-                                    game.try_raise(timeline_num);
-                                }
+                                prompt_bet(&game, timeline).map(|amount| {
+                                    if zeroed {
+                                        Action::Bet { timeline, amount }
+                                    } else {
+                                        Action::Raise { timeline, amount }
+                                    }
+                                })
                             }
-                            Fold => game.fold(timeline_num),
-                            DoNothing => game.skip(timeline_num, t),
+                            AllIn => Some(Action::AllIn { timeline }),
+                            Fold => Some(Action::Fold { timeline }),
+                            Draw => prompt_discards().map(|discards| {
+                                Action::Draw { timeline, discards }
+                            }),
                             ToggleView => {
                                 let new_view =
                                     !game.timelines[timeline_num].boards[t].1;
                                 game.timelines[timeline_num].boards[t].1 =
                                     new_view;
+                                None
                             }
+                        };
+                        if let Some(action) = action {
+                            report(game.apply(action));
                         }
                         ongamechange.emit(game);
                     }
@@ -129,6 +135,9 @@ pub fn GameDisplay(props: &GameDisplayProps) -> Html {
                         {turn_limit}
                         coordinates={(t + timeline.starting_time, timeline_num)}
                         active_player={props.game.get_active_player()}
+                        available={props.game.available_chips(props.game.get_active_player())}
+                        bet_range={props.game.bet_range(timeline_num)}
+                        variant={props.game.rules.variant}
                         {ondragstart}
                         {ondrop}
                         {onbuttonclick}
@@ -138,7 +147,10 @@ pub fn GameDisplay(props: &GameDisplayProps) -> Html {
             }
         }
         html! {
+            <>
+            {history_buttons(props)}
             {for boards}
+            </>
         }
     } else {
         let onclick = {
@@ -150,7 +162,19 @@ pub fn GameDisplay(props: &GameDisplayProps) -> Html {
                 ongameupdate.emit(game);
             }
         };
+        let onsave = {
+            let game = props.game.clone();
+            move |_e: MouseEvent| {
+                if let Some(slot) = prompt_slot() {
+                    if !save_game(&slot, &game) {
+                        gloo_dialogs::alert("The game couldn't be saved");
+                    }
+                }
+            }
+        };
         html! { // hide the board
+            <>
+            {history_buttons(props)}
             <div class="table centered">
                 <div class="player-name" style="animation: none;">
                     <div style="width:200px; padding: 10px;">
@@ -158,7 +182,85 @@ pub fn GameDisplay(props: &GameDisplayProps) -> Html {
                     </div>
                 </div>
                 <button class="start-game do-button" {onclick}>{"Start Turn"}</button>
+                <button class="save-game do-button" onclick={onsave}>{"Save"}</button>
             </div>
+            </>
         }
     }
 }
+
+/// Buttons to take back the last move or make it again, as far as the
+/// rules allow
+fn history_buttons(props: &GameDisplayProps) -> Html {
+    let onundo = {
+        let game = props.game.clone();
+        let ongameupdate = props.ongameupdate.clone();
+        move |_e: MouseEvent| {
+            let mut game = game.clone();
+            if report(game.undo()).is_some() {
+                ongameupdate.emit(game);
+            }
+        }
+    };
+    let onredo = {
+        let game = props.game.clone();
+        let ongameupdate = props.ongameupdate.clone();
+        move |_e: MouseEvent| {
+            let mut game = game.clone();
+            if report(game.redo()).is_some() {
+                ongameupdate.emit(game);
+            }
+        }
+    };
+    html! {
+        <div class="history">
+            <button class="do-button" onclick={onundo} disabled={!props.game.can_undo()}>{"Undo"}</button>
+            <button class="do-button" onclick={onredo} disabled={props.game.undone.is_empty()}>{"Redo"}</button>
+        </div>
+    }
+}
+
+/// Asks the active player how much to bet on a timeline, or `None` if they
+/// cancel
+fn prompt_bet(game: &Multiverse, timeline: usize) -> Option<i64> {
+    let (minimum, maximum) = game.bet_range(timeline);
+    let message = if game.current_turn(timeline).bet_amount == 0 {
+        format!("Enter initial bet ({}–{}⏲)", minimum, maximum)
+    } else {
+        format!("Enter new bet ({}–{}⏲)", minimum, maximum)
+    };
+    gloo_dialogs::prompt(&message, Some(&minimum.to_string()))
+        .and_then(|a| a.parse().ok())
+}
+
+/// Asks the active player which cards to swap, counted from 1, or `None`
+/// if they cancel
+fn prompt_discards() -> Option<Vec<usize>> {
+    let answer = gloo_dialogs::prompt(
+        "Cards to swap, counting from 1 (e.g. \"1 4\"), or none to stand pat",
+        Some(""),
+    )?;
+    answer
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<usize>().ok()?.checked_sub(1))
+        .collect()
+}
+
+/// Asks for a name to save the game under, or `None` if they cancel
+fn prompt_slot() -> Option<String> {
+    let name = gloo_dialogs::prompt("Save the game as", Some("game 1"))?;
+    let name = name.trim();
+    if name.is_empty() || name == AUTOSAVE {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// Tells the player why a move was refused
+fn report<T>(result: Result<T, RuleError>) -> Option<T> {
+    result
+        .map_err(|error| gloo_dialogs::alert(&error.to_string()))
+        .ok()
+}
@@ -1,3 +1,4 @@
+use crate::card_display::*;
 use crate::cards::*;
 use yew::prelude::*;
 
@@ -11,6 +12,10 @@ pub struct PlayerStateDisplay {
 #[derive(PartialEq, Properties)]
 pub struct HandProps {
     pub hand: Vec<Card>,
+    /// cards dealt face up, shown after the hand whether it is visible or
+    /// not
+    #[prop_or_default]
+    pub up_cards: Vec<Card>,
     #[prop_or(true)]
     pub visible: bool,
     #[prop_or_default]
@@ -27,12 +32,17 @@ pub struct HandProps {
 pub fn Hand(props: &HandProps) -> Html {
     let cards = props.hand.iter().enumerate().map(|(i, card)| {
         html! {
-            <CardDisplay card={card.clone()}
+            <CardDisplay card={*card}
                 visible={props.visible}
                 draggable={props.draggable}
                 ondragstart={props.ondragstart.reform(move |e| (e, i))}/>
         }
     });
+    let up_cards = props.up_cards.iter().map(|card| {
+        html! {
+            <CardDisplay card={*card} visible={true} />
+        }
+    });
     let player_state = if let Some(ps) = props.playerstate.clone() {
         html! {
 
@@ -43,7 +53,7 @@ pub fn Hand(props: &HandProps) -> Html {
     };
     html! {
         <div class="hand-of-cards" style={props.style.to_string()}>
-            {for cards}<br/>
+            {for cards}{for up_cards}<br/>
             {player_state}
         </div>
     }
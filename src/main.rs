@@ -1,5 +1,7 @@
+mod action;
 pub mod board;
 pub mod board_display;
+mod card_display;
 mod cards;
 mod game;
 pub mod game_display;
@@ -7,11 +9,19 @@ mod hand;
 pub mod multiverse;
 mod new_game;
 pub mod player;
+mod pot;
+mod rng;
+mod rules;
+pub mod save;
+mod storage;
 
+use action::*;
 use game::*;
 use game_display::*;
 use multiverse::*;
 use new_game::*;
+use rules::*;
+use storage::*;
 use yew::prelude::*;
 
 fn main() {
@@ -19,13 +29,23 @@ fn main() {
     yew::Renderer::<App>::new().render();
 }
 
+/// Saves the game so that it can be resumed, warning the player if it can't
+fn autosave(game: &Multiverse) {
+    if !save_game(AUTOSAVE, game) {
+        gloo_dialogs::alert(
+            "The game couldn't be saved, so it can't be resumed from here",
+        );
+    }
+}
+
 #[function_component]
 fn App() -> Html {
     let active_game: UseStateHandle<Option<Multiverse>> = use_state(|| None);
     if let Some(game) = (*active_game).clone() {
         let ongameupdate = {
             let active_game = active_game.clone();
-            move |game| {
+            move |game: Multiverse| {
+                autosave(&game);
                 active_game.set(Some(game));
             }
         };
@@ -35,7 +55,8 @@ fn App() -> Html {
     } else {
         let ongamecreate = {
             let active_game = active_game.clone();
-            move |game| {
+            move |game: Multiverse| {
+                autosave(&game);
                 active_game.set(Some(game));
             }
         };
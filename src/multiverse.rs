@@ -1,57 +1,195 @@
+use crate::action::*;
 use crate::board::*;
-use crate::cards::calculate_winners;
+use crate::cards::{calculate_winners, Card, Provenance};
 use crate::game::*;
 use crate::player::*;
+use crate::pot::*;
+use crate::rng::SplitMix64;
+use crate::rules::*;
 use rand::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Multiverse {
     pub players: Vec<Player>,
     pub timelines: Vec<Timeline>,
     pub active_player: usize, // cache, so that the board can be hidden.
+    pub rules: GameRules,
+    /// chips spent on time travel, which leave the game for good
+    pub burned_chips: i64,
+    /// seed the game was created with, which decides every shuffle
+    pub seed: u64,
+    /// draws the seed for each action from the game's seed
+    pub rng: SplitMix64,
+    /// shuffles for the action being applied, seeded from its log entry
+    pub action_rng: SplitMix64,
+    /// every action applied so far, in order
+    pub log: Vec<LoggedAction>,
+    /// actions taken back, the latest last, until another move is made
+    pub undone: Vec<LoggedAction>,
 }
 
 impl Multiverse {
-    pub fn from_players(players: Vec<String>) -> Self {
-        let num_players = players.len();
+    pub fn from_players(players: Vec<String>, rules: GameRules) -> Self {
+        Self::from_seed(players, rules, thread_rng().gen())
+    }
+
+    /// A new game whose shuffles all follow from `seed`, so that the same
+    /// seed and moves always play out the same way
+    pub fn from_seed(
+        players: Vec<String>,
+        rules: GameRules,
+        seed: u64,
+    ) -> Self {
+        let mut rng = SplitMix64::new(seed);
+        let stacks = vec![rules.starting_stack; players.len()];
+        let first = Timeline::genesis(&stacks, &rules, &mut rng);
+        // what is posted on the first timeline can't be posted again
+        let stacks: Vec<i64> = first.boards[0].0[0]
+            .player_states
+            .iter()
+            .zip(stacks)
+            .map(|(state, stack)| stack - state.commitment())
+            .collect();
+        let second = Timeline::genesis(&stacks, &rules, &mut rng);
         Self {
             players: players
                 .into_iter()
-                .map(|name| Player::from_name(name))
+                .map(|name| Player::new(name, rules.starting_stack))
                 .collect(),
-            timelines: vec![
-                Timeline::genesis(num_players),
-                Timeline::genesis(num_players),
-            ],
+            timelines: vec![first, second],
             active_player: usize::MAX, // cannot be a player
+            rules,
+            burned_chips: 0,
+            seed,
+            rng,
+            action_rng: SplitMix64::new(seed),
+            log: vec![],
+            undone: vec![],
         }
     }
 
-    /// returns the indices of the new timeline in same order as arguments
-    pub fn spawn_timeline(
+    /// Plays the logged actions again on a new game with the same players,
+    /// rules and seed. Shuffles use the logged seeds, so this rebuilds the
+    /// game exactly, apart from what the players chose to look at.
+    pub fn replay(&self) -> Result<Self, RuleError> {
+        self.replay_log(&self.log)
+    }
+
+    fn replay_log(&self, log: &[LoggedAction]) -> Result<Self, RuleError> {
+        let players = self
+            .players
+            .iter()
+            .map(|player| player.name.clone())
+            .collect();
+        Self::from_log(players, self.rules, self.seed, log)
+    }
+
+    /// A new game from `seed` with the logged actions played on it
+    pub fn from_log(
+        players: Vec<String>,
+        rules: GameRules,
+        seed: u64,
+        log: &[LoggedAction],
+    ) -> Result<Self, RuleError> {
+        let mut game = Self::from_seed(players, rules, seed);
+        for entry in log {
+            game.apply_seeded(entry.action.clone(), entry.seed)?;
+        }
+        Ok(game)
+    }
+
+    /// Whether the last move can be taken back under the rules
+    pub fn can_undo(&self) -> bool {
+        let Some(last) = self.log.last() else {
+            return false;
+        };
+        match self.rules.undo {
+            UndoPolicy::Never => false,
+            // the next player hasn't revealed the boards yet
+            UndoPolicy::ThisTurn => last.player == self.active_player,
+            UndoPolicy::Always => true,
+        }
+    }
+
+    /// Takes back the last move by playing the game again without it. What
+    /// the players were looking at stays as it was.
+    pub fn undo(&mut self) -> Result<(), RuleError> {
+        if !self.can_undo() {
+            return Err(RuleError::CannotUndo);
+        }
+        let (last, log) = self.log.split_last().unwrap();
+        let mut game = self.replay_log(log)?;
+        game.active_player = self.active_player;
+        for (timeline, old) in game.timelines.iter_mut().zip(&self.timelines) {
+            for (board, old) in timeline.boards.iter_mut().zip(&old.boards) {
+                board.1 = old.1;
+            }
+        }
+        game.undone = std::mem::take(&mut self.undone);
+        game.undone.push(last.clone());
+        *self = game;
+        Ok(())
+    }
+
+    /// Makes the last move that was taken back again
+    pub fn redo(&mut self) -> Result<Event, RuleError> {
+        let next = self.undone.pop().ok_or(RuleError::CannotUndo)?;
+        self.apply_seeded(next.action.clone(), next.seed)
+            .inspect_err(|_| self.undone.push(next))
+    }
+
+    /// returns the indices of the new timeline in same order as arguments.
+    /// The new timeline shares its history with the parent up to `turn`.
+    fn spawn_timeline(
         &mut self,
         parent_index: usize,
         starting_time: usize,
+        turn: usize,
     ) -> (usize, usize) {
         let target_timeline = &self.timelines[parent_index];
         let target_board = &target_timeline.boards
             [starting_time - target_timeline.starting_time];
-        let mut boards = vec![];
-        /* for i in 0..starting_time {
-            boards.push(Board::new(vec![], 0));
-        } */
-        boards.push(Board::timeline_intersect(target_board, self.get_turn()));
+        let boards = vec![Board::timeline_intersect(target_board, turn)];
         self.timelines.push(Timeline {
             parent_index,
-            starting_time: starting_time,
-            boards: boards,
+            starting_time,
+            boards,
         });
+        // the bets on the branch are made again, so whoever can't cover them
+        // is all in for what they have left
+        for player in 0..self.players.len() {
+            let mut short = -self.available_chips(player);
+            if short <= 0 {
+                continue;
+            }
+            let turn = self.timelines.last_mut().unwrap().current_board_mut();
+            let turn = turn.0.last_mut().unwrap();
+            let state = &mut turn.player_states[player];
+            state.round_bet -= short.min(state.round_bet);
+            while let Some(bet) = state.bet.last_mut().filter(|_| short > 0) {
+                let taken = short.min(*bet);
+                *bet -= taken;
+                short -= taken;
+                if *bet == 0 {
+                    state.bet.pop();
+                }
+            }
+            state.all_in = true;
+            if turn.to_act == player && !turn.is_drawing(&self.rules) {
+                turn.to_act = turn.next_to_act(player);
+            }
+        }
         // will always be the first index
         (self.timelines.len() - 1, 0)
     }
 
+    /// Players take turns in seat order, starting with the first to act in
+    /// the opening hand. A player only moves on the boards where the action
+    /// is on them; the others are skipped for them.
     pub fn get_active_player(&self) -> usize {
-        return self.get_turn() % self.players.len();
+        let first = self.timelines[0].boards[0].0[0].to_act;
+        (first + self.get_turn()) % self.players.len()
     }
 
     pub fn get_turn(&self) -> usize {
@@ -64,6 +202,29 @@ impl Multiverse {
         turn - 1
     }
 
+    /// Checks that the active player can move on the board in play on a
+    /// timeline: they haven't moved there yet this turn, and the action is
+    /// on them
+    pub fn check_can_act(&self, timeline: usize) -> Result<(), RuleError> {
+        let board = self
+            .timelines
+            .get(timeline)
+            .ok_or(RuleError::InvalidTimeline { timeline })?
+            .current_board();
+        let turn = self.get_turn();
+        let state = board.get_turn(Some(turn));
+        let player = &state.player_states[state.to_act];
+        if board.is_past(Some(turn))
+            || state.is_finished()
+            || state.to_act != self.get_active_player()
+            || player.folded
+            || (player.all_in && !state.is_drawing(&self.rules))
+        {
+            return Err(RuleError::OutOfTurn);
+        }
+        Ok(())
+    }
+
     pub fn current_turn(&self, timeline: usize) -> &Turn {
         self.timelines[timeline]
             .boards
@@ -72,216 +233,1464 @@ impl Multiverse {
             .get_turn(Some(self.get_turn()))
     }
 
-    pub fn try_initial_bet(&mut self, timeline: usize) -> bool {
-        if let Some(amount) =
-            gloo_dialogs::prompt("Enter initial bet (min 1⏲)", None)
-                .and_then(|a| a.parse().ok())
-        {
-            if amount >= 1 {
-                return self.try_bet(timeline, amount, false);
+    /// Validates an action and carries it out for the active player
+    pub fn apply(&mut self, action: Action) -> Result<Event, RuleError> {
+        let event = self.apply_seeded(action, self.rng.clone().next_u64())?;
+        self.undone.clear();
+        Ok(event)
+    }
+
+    /// Applies an action, shuffling with `seed` if it ends a hand, and logs
+    /// it if it succeeds
+    fn apply_seeded(
+        &mut self,
+        action: Action,
+        seed: u64,
+    ) -> Result<Event, RuleError> {
+        let player = self.get_active_player();
+        // every hand the action ends is shuffled from this in turn, so that
+        // no two get the same shuffle
+        let previous =
+            std::mem::replace(&mut self.action_rng, SplitMix64::new(seed));
+        let event = self
+            .apply_action(action.clone())
+            .inspect_err(|_| self.action_rng = previous)?;
+        self.log.push(LoggedAction {
+            action,
+            seed,
+            player,
+        });
+        // only moves that went through use up a seed
+        self.rng.next_u64();
+        self.pass_busted_players();
+        self.skip_idle_boards();
+        self.check_cards();
+        Ok(event)
+    }
+
+    fn apply_action(&mut self, action: Action) -> Result<Event, RuleError> {
+        match action {
+            Action::Check { timeline } => {
+                self.try_check(timeline)?;
+                Ok(Event::Checked)
+            }
+            Action::Call { timeline } => {
+                self.try_call(timeline)?;
+                Ok(Event::Called)
+            }
+            Action::Bet { timeline, amount } => {
+                self.try_initial_bet(timeline, amount)?;
+                Ok(Event::Bet { amount })
+            }
+            Action::Raise { timeline, amount } => {
+                self.try_raise(timeline, amount)?;
+                Ok(Event::Raised { amount })
+            }
+            Action::AllIn { timeline } => {
+                let amount = self.try_all_in(timeline)?;
+                Ok(Event::AllIn { amount })
+            }
+            Action::Fold { timeline } => {
+                self.fold(timeline)?;
+                Ok(Event::Folded)
             }
+            Action::Draw { timeline, discards } => {
+                let count = self.try_draw(timeline, &discards)?;
+                Ok(Event::Drew { count })
+            }
+            Action::TimeTravel {
+                from,
+                to,
+                card,
+                raise,
+            } => self.time_travel(from, to, card, raise),
         }
-        return false;
     }
 
-    pub fn try_raise_or_bet(&mut self, timeline: usize) -> bool {
-        let output = if self.current_turn(timeline).bet_amount == 0 {
-            self.try_initial_bet(timeline)
+    /// Moves a card between boards. The active player has to raise on the
+    /// timeline the card leaves, and burns chips as given by
+    /// `time_travel_cost`. The card has to come from the board in play on
+    /// its timeline; sending it to a board that has already moved this turn
+    /// splits off a new timeline.
+    fn time_travel(
+        &mut self,
+        from: BoardCoordinate,
+        to: BoardCoordinate,
+        card: CardLocation,
+        raise: i64,
+    ) -> Result<Event, RuleError> {
+        let burned = self.time_travel_cost(from, to, card)?;
+        let turn_limit = self.get_turn();
+        let initiating_player = self.get_active_player();
+        self.check_raise_or_bet(from.timeline, raise)?;
+        let target = &self.timelines[to.timeline].boards[to.board];
+        // sending a card into the past branches the timeline, which puts
+        // the player's bets there in again
+        let branched = if target.is_past(Some(turn_limit)) {
+            target.get_turn(Some(turn_limit)).player_states[initiating_player]
+                .commitment()
         } else {
-            self.try_raise(timeline)
+            0
         };
-        if output {
-            let turn_limit = self.get_turn();
-            self.timelines[timeline]
-                .boards
-                .last_mut()
-                .unwrap()
-                .get_turn_mut(Some(turn_limit))
-                .num_checks = 1;
-        }
-        output
-    }
-
-    pub fn try_raise(&mut self, timeline: usize) -> bool {
-        // let min_amount = 2 * self.current_turn(timeline).bet_amount; // TODO
-        let min_amount = 1 + self.current_turn(timeline).bet_amount;
-        if let Some(amount) = gloo_dialogs::prompt(
-            &format!(
-                "Enter new bet (min {}⏲ )",
-                min_amount
-            ),
-            None,
-        )
-        .and_then(|a| a.parse::<i64>().ok())
-        {
-            if amount >= min_amount {
-                return self.try_bet(timeline, amount, false);
+        let available = self.available_chips(initiating_player) - branched;
+        let added = raise
+            - self.current_turn(from.timeline).player_states[initiating_player]
+                .round_bet;
+        if available < burned + added {
+            return Err(RuleError::InsufficientChips { available });
+        }
+
+        // the card leaves before the raise goes in, in case the raise ends
+        // the hand
+        let source = &mut self.timelines[from.timeline].boards[from.board];
+        let mut moved = source.0[turn_limit].pile(card.player)[card.index];
+        source.edit_from(turn_limit, |turn| {
+            let pile = turn.pile_mut(card.player);
+            if let Some(i) = pile.iter().position(|&other| other == moved) {
+                pile.remove(i);
+                turn.departures.push(moved);
             }
+        });
+        let from_time =
+            from.board + self.timelines[from.timeline].starting_time;
+        let origin = *moved.origin.get_or_insert(Provenance {
+            timeline: from.timeline,
+            time: from_time,
+            owner: card.player,
+        });
+
+        let to = if self.timelines[to.timeline].boards[to.board]
+            .is_past(Some(turn_limit))
+        {
+            let time = to.board + self.timelines[to.timeline].starting_time;
+            let (timeline, board) =
+                self.spawn_timeline(to.timeline, time, turn_limit);
+            BoardCoordinate { timeline, board }
+        } else {
+            to
+        };
+        let to_time = to.board + self.timelines[to.timeline].starting_time;
+        if origin.timeline == to.timeline && origin.time == to_time {
+            // back where it was dealt
+            moved.origin = None;
+        }
+        let policy = self.rules.paradoxes;
+        self.timelines[to.timeline].boards[to.board].edit_from(
+            turn_limit,
+            |turn| {
+                turn.pile_mut(card.player).push(moved);
+                turn.arrivals.push(moved);
+                turn.resolve_paradoxes(policy);
+            },
+        );
+        self.burn_chips(initiating_player, burned);
+        self.place_bet(from.timeline, raise, false);
+        Ok(Event::TimeTravelled {
+            burned,
+            timeline: to.timeline,
+            board: to.board,
+        })
+    }
+
+    /// Chips burned by moving a card between boards: so much for every step
+    /// through time and every timeline crossed, plus a surcharge for an open
+    /// card, as set in the rules. Moves that can't be made are refused here
+    /// already, apart from the raise that goes with them.
+    pub fn time_travel_cost(
+        &self,
+        from: BoardCoordinate,
+        to: BoardCoordinate,
+        card: CardLocation,
+    ) -> Result<i64, RuleError> {
+        if from == to {
+            return Err(RuleError::SameBoard);
+        }
+        if card
+            .player
+            .is_some_and(|player| player != self.get_active_player())
+        {
+            return Err(RuleError::NotYourCard);
+        }
+        let from_time = self.board_time(from)?;
+        let to_time = self.board_time(to)?;
+        let turn =
+            self.timelines[from.timeline].boards[from.board].get_turn(None);
+        let cards = match card.player {
+            Some(player) => turn
+                .player_states
+                .get(player)
+                .map(|state| &state.hand)
+                .ok_or(RuleError::CardMissing)?,
+            None => &turn.open_cards,
+        };
+        if card.index >= cards.len() {
+            return Err(RuleError::CardMissing);
+        }
+        if from.board + 1 != self.timelines[from.timeline].boards.len() {
+            return Err(RuleError::InvalidTime {
+                timeline: from.timeline,
+                board: from.board,
+            });
+        }
+        self.check_can_act(from.timeline)?;
+        self.check_street(from.timeline, false)?;
+        let target = self.timelines[to.timeline].boards[to.board]
+            .get_turn(Some(self.get_turn()));
+        if target.is_finished() {
+            return Err(RuleError::BoardFinished);
+        }
+        if self.rules.paradoxes == ParadoxPolicy::Forbid
+            && target
+                .cards_in_play()
+                .any(|other| other.same_face(&cards[card.index]))
+        {
+            return Err(RuleError::Paradox);
+        }
+        let rules = &self.rules;
+        Ok((from_time as i64 - to_time as i64).abs() * rules.time_cost
+            + (from.timeline as i64 - to.timeline as i64).abs()
+                * rules.timeline_cost
+            + if card.player.is_some() {
+                0
+            } else {
+                rules.open_card_cost
+            })
+    }
+
+    /// The absolute time of a board, checking that it exists
+    fn board_time(&self, at: BoardCoordinate) -> Result<usize, RuleError> {
+        let timeline = self.timelines.get(at.timeline).ok_or(
+            RuleError::InvalidTimeline {
+                timeline: at.timeline,
+            },
+        )?;
+        if at.board >= timeline.boards.len() {
+            return Err(RuleError::InvalidTime {
+                timeline: at.timeline,
+                board: at.board,
+            });
+        }
+        Ok(timeline.starting_time + at.board)
+    }
+
+    fn try_initial_bet(
+        &mut self,
+        timeline: usize,
+        amount: i64,
+    ) -> Result<(), RuleError> {
+        self.check_can_act(timeline)?;
+        if self.current_turn(timeline).bet_amount != 0 {
+            return Err(RuleError::AlreadyBet);
         }
-        false
+        self.check_raise(timeline, amount)?;
+        self.try_bet(timeline, amount, false)
     }
 
-    pub fn can_bet(
+    /// Checks that the active player could open or raise to `amount` on a
+    /// timeline, without putting the bet in
+    fn check_raise_or_bet(
         &self,
         timeline: usize,
-        _player: usize,
         amount: i64,
-    ) -> bool {
-        return amount
-            >= self.timelines[timeline]
-                .boards
-                .last()
-                .unwrap()
-                .get_turn(Some(self.get_turn()))
-                .bet_amount;
+    ) -> Result<(), RuleError> {
+        self.check_can_act(timeline)?;
+        self.check_street(timeline, false)?;
+        self.check_raise(timeline, amount)?;
+        let player = self.get_active_player();
+        if !self.can_bet(timeline, player, amount) {
+            let available = self.available_chips(player);
+            return Err(RuleError::InsufficientChips { available });
+        }
+        Ok(())
     }
 
-    pub fn try_check(&mut self, timeline: usize) -> bool {
+    fn try_raise(
+        &mut self,
+        timeline: usize,
+        amount: i64,
+    ) -> Result<(), RuleError> {
+        self.check_can_act(timeline)?;
         if self.current_turn(timeline).bet_amount == 0 {
-            let mut turn = self.current_turn(timeline).clone();
-            turn.num_checks += 1;
-            self.timelines[timeline].current_board_mut().0.push(turn);
-            self.try_increase_stage(timeline);
-            return true;
-        }
-        return false;
-    }
-
-    pub fn try_bet(&mut self, timeline: usize, amount: i64, check: bool) -> bool {
-        if self.can_bet(timeline, self.get_active_player(), amount) {
-            let mut turn = self.current_turn(timeline).clone();
-            turn.player_states[self.get_active_player()]
-                .bet
-                .push(amount);
-            turn.bet_amount = amount;
-            if check {
-                turn.num_checks += 1;
-            } else {
-                turn.num_checks = 1;
-            };
-            self.timelines[timeline].current_board_mut().0.push(turn);
-            self.try_increase_stage(timeline);
-            return true;
+            return Err(RuleError::NothingToRaise);
         }
-        return false;
+        self.check_raise(timeline, amount)?;
+        self.try_bet(timeline, amount, false)
     }
 
-    pub fn try_call(&mut self, timeline: usize) -> bool {
-        let bet_amount = self.timelines[timeline]
-            .boards
-            .last()
-            .unwrap()
-            .get_turn(Some(self.get_turn()))
-            .bet_amount;
-        if self.can_bet(timeline, self.get_active_player(), bet_amount) {
-            return self.try_bet(timeline, bet_amount, true);
+    /// Smallest and largest amounts the active player can open or raise to
+    /// on a timeline, given the betting structure and their chips. The
+    /// minimum is above the maximum when they are too short to raise.
+    pub fn bet_range(&self, timeline: usize) -> (i64, i64) {
+        let (minimum, maximum) = self.raise_limits(timeline);
+        let player = self.get_active_player();
+        let stack = self.current_turn(timeline).player_states[player].round_bet
+            + self.available_chips(player);
+        (minimum, maximum.unwrap_or(stack).min(stack))
+    }
+
+    /// The range of legal bets from the betting structure alone. `None`
+    /// means there is no upper limit.
+    fn raise_limits(&self, timeline: usize) -> (i64, Option<i64>) {
+        let turn = self.current_turn(timeline);
+        let bet = turn.bet_amount;
+        let big_blind = self.rules.big_blind;
+        let min_raise = turn.last_raise.max(big_blind);
+        match self.rules.betting {
+            BettingStructure::NoLimit => (bet + min_raise, None),
+            BettingStructure::PotLimit => {
+                let pot: i64 =
+                    turn.player_states.iter().map(|p| p.commitment()).sum();
+                let to_call = turn.to_call(self.get_active_player());
+                (bet + min_raise, Some(bet + pot + to_call))
+            }
+            BettingStructure::Limit => {
+                let size = if self.rules.variant.betting_round(turn.street) < 2
+                {
+                    big_blind
+                } else {
+                    2 * big_blind
+                };
+                (bet + size, Some(bet + size))
+            }
         }
-        return false;
     }
 
-    pub fn fold(&mut self, timeline: usize) {
-        let active_player = self.get_active_player();
-        let mut state = self.timelines[timeline]
-            .current_board()
-            .0
-            .last()
-            .unwrap()
-            .clone();
-        state.player_states[active_player].folded = true;
+    fn check_raise(
+        &self,
+        timeline: usize,
+        amount: i64,
+    ) -> Result<(), RuleError> {
+        let (minimum, maximum) = self.raise_limits(timeline);
+        if amount < minimum {
+            return Err(RuleError::BetTooSmall { minimum });
+        }
+        match maximum {
+            Some(maximum) if amount > maximum => {
+                Err(RuleError::BetTooLarge { maximum })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether `player` can bet `amount` on the street: enough to match the
+    /// current bet, and no more than they have left
+    pub fn can_bet(&self, timeline: usize, player: usize, amount: i64) -> bool {
+        let turn = self.current_turn(timeline);
+        amount >= turn.bet_amount
+            && amount - turn.player_states[player].round_bet
+                <= self.available_chips(player)
+    }
+
+    /// Checks that the street on a timeline is for drawing if `draw`, and
+    /// for betting otherwise
+    fn check_street(
+        &self,
+        timeline: usize,
+        draw: bool,
+    ) -> Result<(), RuleError> {
+        let streets = self.rules.variant.streets();
+        let street = streets.get(self.current_turn(timeline).street);
+        if (street == Some(&Street::Draw)) != draw {
+            return Err(RuleError::WrongStreet);
+        }
+        Ok(())
+    }
+
+    fn try_check(&mut self, timeline: usize) -> Result<(), RuleError> {
+        self.check_can_act(timeline)?;
+        self.check_street(timeline, false)?;
+        let to_call = self
+            .current_turn(timeline)
+            .to_call(self.get_active_player());
+        if to_call != 0 {
+            return Err(RuleError::BetToCall { amount: to_call });
+        }
+        let mut turn = self.current_turn(timeline).clone();
+        turn.num_checks += 1;
+        turn.to_act = turn.next_to_act(turn.to_act);
+        self.timelines[timeline].current_board_mut().0.push(turn);
+        self.try_increase_stage(timeline);
+        Ok(())
+    }
+
+    fn try_bet(
+        &mut self,
+        timeline: usize,
+        amount: i64,
+        check: bool,
+    ) -> Result<(), RuleError> {
+        self.check_can_act(timeline)?;
+        self.check_street(timeline, false)?;
+        let player = self.get_active_player();
+        if !self.can_bet(timeline, player, amount) {
+            let available = self.available_chips(player);
+            if amount >= self.current_turn(timeline).bet_amount {
+                return Err(RuleError::InsufficientChips { available });
+            }
+            return Err(RuleError::BetTooSmall {
+                minimum: self.current_turn(timeline).bet_amount,
+            });
+        }
+        self.place_bet(timeline, amount, check);
+        Ok(())
+    }
+
+    fn try_call(&mut self, timeline: usize) -> Result<(), RuleError> {
+        self.check_can_act(timeline)?;
+        let bet_amount = self.current_turn(timeline).bet_amount;
+        self.try_bet(timeline, bet_amount, true)
+    }
+
+    /// Puts in everything the active player has left. This calls when they
+    /// are short of the current bet, and raises otherwise. Returns what
+    /// their bet on the street comes to.
+    fn try_all_in(&mut self, timeline: usize) -> Result<i64, RuleError> {
+        self.check_can_act(timeline)?;
+        self.check_street(timeline, false)?;
+        let player = self.get_active_player();
+        let available = self.available_chips(player).max(0);
+        let turn = self.current_turn(timeline);
+        let amount = turn.player_states[player].round_bet + available;
+        let bet_amount = turn.bet_amount;
+        if let (_, Some(maximum)) = self.raise_limits(timeline) {
+            if amount > maximum {
+                return Err(RuleError::BetTooLarge { maximum });
+            }
+        }
+        self.place_bet(timeline, amount, amount <= bet_amount);
+        Ok(amount)
+    }
+
+    /// Brings the active player's bet on the street up to `amount` without
+    /// checking it. A player who puts in everything they have left is all
+    /// in, and is no longer counted among the players who need to call.
+    fn place_bet(&mut self, timeline: usize, amount: i64, check: bool) {
+        let player = self.get_active_player();
+        let mut turn = self.current_turn(timeline).clone();
+        let state = &mut turn.player_states[player];
+        let chips = amount - state.round_bet;
+        let all_in = chips >= self.available_chips(player);
+        state.bet.push(chips);
+        state.round_bet = amount;
+        state.all_in = all_in;
+        turn.last_raise = turn.last_raise.max(amount - turn.bet_amount);
+        turn.bet_amount = turn.bet_amount.max(amount);
+        if !check {
+            turn.num_checks = 0;
+        }
+        if !all_in {
+            turn.num_checks += 1;
+        }
+        turn.to_act = turn.next_to_act(player);
+        self.timelines[timeline].current_board_mut().0.push(turn);
+        self.try_increase_stage(timeline);
+    }
+
+    /// Chips a player has left to bet, after what they have already
+    /// committed to the hands in progress on every timeline
+    pub fn available_chips(&self, player: usize) -> i64 {
+        let committed: i64 = self
+            .timelines
+            .iter()
+            .map(|timeline| {
+                timeline.current_board().get_turn(None).player_states[player]
+                    .commitment()
+            })
+            .sum();
+        self.players[player].chips - committed
+    }
+
+    fn fold(&mut self, timeline: usize) -> Result<(), RuleError> {
+        self.check_can_act(timeline)?;
+        let mut state = self.current_turn(timeline).clone();
+        // a hand can be dealt to one player, when nobody else has chips
+        // left to play it; they check it down to take their chips back
+        if state.player_states.iter().filter(|p| !p.folded).count() < 2 {
+            return Err(RuleError::LastInHand);
+        }
+        state.player_states[self.get_active_player()].folded = true;
+        state.to_act = if state.is_drawing(&self.rules) {
+            state.next_to_draw(state.to_act)
+        } else {
+            state.next_to_act(state.to_act)
+        };
         self.timelines[timeline].current_board_mut().0.push(state);
         self.try_increase_stage(timeline);
+        Ok(())
     }
 
-    pub fn skip(&mut self, timeline: usize, epoch: usize) {
-        let board = &mut self.timelines[timeline].boards[epoch];
-        board.0.push(board.0.last().unwrap().clone());
+    /// Swaps the cards at `discards` in the active player's hand for new
+    /// ones from the deck. Returns how many they got, which is fewer than
+    /// they gave up if the deck runs out.
+    fn try_draw(
+        &mut self,
+        timeline: usize,
+        discards: &[usize],
+    ) -> Result<usize, RuleError> {
+        self.check_can_act(timeline)?;
+        self.check_street(timeline, true)?;
+        let player = self.get_active_player();
+        let mut turn = self.current_turn(timeline).clone();
+        let mut discards = discards.to_vec();
+        discards.sort_unstable();
+        discards.dedup();
+        let hand = &mut turn.player_states[player].hand;
+        if discards.last().is_some_and(|&i| i >= hand.len()) {
+            return Err(RuleError::CardMissing);
+        }
+        for &i in discards.iter().rev() {
+            turn.discards.push(hand.remove(i));
+        }
+        let drawn = turn.deal(discards.len());
+        let count = drawn.len();
+        turn.player_states[player].hand.extend(drawn);
+        turn.num_checks += 1;
+        turn.to_act = turn.next_to_draw(player);
+        self.timelines[timeline].current_board_mut().0.push(turn);
+        self.try_increase_stage(timeline);
+        Ok(count)
+    }
+
+    /// A player who has committed all their chips on other timelines is all
+    /// in wherever the action comes to them, as they have nothing left to
+    /// bet with. This can close the betting round. They still draw.
+    fn pass_busted_players(&mut self) {
+        for timeline in 0..self.timelines.len() {
+            loop {
+                let turn =
+                    self.timelines[timeline].current_board().get_turn(None);
+                let player = turn.to_act;
+                let state = &turn.player_states[player];
+                if turn.is_finished()
+                    || turn.is_drawing(&self.rules)
+                    || state.folded
+                    || state.all_in
+                    || self.available_chips(player) > 0
+                {
+                    break;
+                }
+                let turn = self.timelines[timeline]
+                    .current_board_mut()
+                    .0
+                    .last_mut()
+                    .unwrap();
+                turn.player_states[player].all_in = true;
+                turn.to_act = turn.next_to_act(player);
+                self.try_increase_stage(timeline);
+            }
+        }
     }
 
-    pub fn try_increase_stage(&mut self, timeline: usize) {
+    /// Passes on every board the active player can't move on this turn, so
+    /// that the turn moves on once they have moved everywhere they can.
+    /// Keeps going while whole turns pass without anyone able to move, for
+    /// at most one round of the table.
+    fn skip_idle_boards(&mut self) {
+        for _ in 0..self.players.len() {
+            let turn = self.get_turn();
+            for timeline in 0..self.timelines.len() {
+                let can_act = self.check_can_act(timeline).is_ok();
+                let boards = &mut self.timelines[timeline].boards;
+                let current = boards.len() - 1;
+                for (i, board) in boards.iter_mut().enumerate() {
+                    if !board.is_past(Some(turn)) && (i != current || !can_act)
+                    {
+                        board.0.push(board.0.last().unwrap().clone());
+                    }
+                }
+            }
+            if self.get_turn() == turn {
+                break;
+            }
+        }
+    }
+
+    fn try_increase_stage(&mut self, timeline: usize) {
         let board_num = self.timelines[timeline].boards.len() - 1;
         let board_turn = self.timelines[timeline].boards[board_num].0.len() - 1;
-        let mut state =
+        let state =
             &mut self.timelines[timeline].boards[board_num].0[board_turn];
 
-        let players_not_folded = state
+        let players_not_folded =
+            state.player_states.iter().filter(|p| !p.folded).count();
+        // everyone still in the hand draws, but only those with chips bet
+        let drawing = state.is_drawing(&self.rules);
+        let players_to_act = state
             .player_states
             .iter()
-            .filter(|p| !p.folded)
-            .collect::<Vec<_>>()
-            .len();
+            .filter(|p| !p.folded && (drawing || !p.all_in))
+            .count();
 
-        let mut next_stage = 0;
+        let streets = self.rules.variant.streets();
+        log::info!("{}", state.num_checks);
         if players_not_folded <= 1 {
-            next_stage = 4;
+            state.street = streets.len();
+        } else if state.num_checks >= players_to_act {
+            state.next_round();
+            state.deal_streets(&self.rules);
         }
-        log::info!("{}", state.num_checks);
-        if next_stage > state.completed_stage
-            || state.num_checks >= players_not_folded
-        {
-            state.num_checks = 0;
-            state.completed_stage = next_stage.max(state.completed_stage + 1);
-            match state.completed_stage {
-                1 => {
-                    // draw the initial 3 cards
-                    state.bet_amount = 0;
-                    state.open_cards.push(state.deck.pop().unwrap());
-                    state.open_cards.push(state.deck.pop().unwrap());
-                    state.open_cards.push(state.deck.pop().unwrap());
-                }
-                2 | 3 => {
-                    // draw the extra card
-                    state.bet_amount = 0;
-                    state.open_cards.push(state.deck.pop().unwrap());
-                }
-                4 => {
-                    // reveal a fifth here for hold'em
-                    // showdown
-                    let winning_hand_type = self.showdown(timeline);
-                    let mut state = &mut self.timelines[timeline].boards
-                        [board_num]
-                        .0[board_turn];
-                    state.winning_hand_type = Some(winning_hand_type);
-                }
-                _ => { /* unreachable */ }
-            }
+        if state.street >= streets.len() {
+            // showdown
+            let winning_hand_type = self.showdown(timeline);
+            let state =
+                &mut self.timelines[timeline].boards[board_num].0[board_turn];
+            state.winning_hand_type = Some(winning_hand_type);
         }
     }
 
-    pub fn showdown(&mut self, timeline: usize) -> crate::cards::HandType {
-        let state = &mut self.timelines[timeline]
+    fn showdown(&mut self, timeline: usize) -> crate::cards::HandType {
+        let state = self.timelines[timeline]
             .boards
             .last_mut()
             .unwrap()
             .0
-            .last()
+            .last_mut()
             .unwrap();
-        let hands = (0..self.players.len())
-            .filter(|i| !state.player_states[i.clone()].folded)
-            .map(|i| (i, state.player_states[i].hand.clone()))
+        // the board may have dealt a card that time travel had already
+        // brought into play
+        state.resolve_paradoxes(self.rules.paradoxes);
+        let state = &*state;
+        let variant = self.rules.variant;
+        let cards = |i: usize| {
+            let player = &state.player_states[i];
+            let hole = [player.hand.as_slice(), &player.up_cards].concat();
+            (i, variant.hand_value(&hole, &state.open_cards))
+        };
+        let dealer = state.dealer;
+        let committed: Vec<i64> = state
+            .player_states
+            .iter()
+            .map(|player| player.commitment())
             .collect();
-        let (winners, winning_hand) = calculate_winners(&hands);
-        let mut deck = state.deck.clone();
-        for i in state.player_states.iter() {
-            deck.append(&mut i.hand.clone());
-        }
-        deck.append(&mut state.open_cards.clone());
-        let mut winnings: Vec<f64> =
-            (0..state.player_states.len()).map(|_| 0_f64).collect();
-        for (i, player) in state.player_states.iter().enumerate() {
-            winnings[i] -= player.commitment() as f64;
-            for winner in &winners {
-                winnings[winner.clone()] +=
-                    player.commitment() as f64 / winners.len() as f64;
+        let mut winnings: Vec<i64> =
+            committed.iter().map(|&chips| -chips).collect();
+        let mut winning_hand = None;
+        for pot in build_pots(&state.player_states) {
+            let hands: Vec<_> =
+                pot.eligible.iter().map(|&i| cards(i)).collect();
+            let (winners, value) = calculate_winners(&hands);
+            // the main pot decides the hand that gets announced
+            winning_hand.get_or_insert(value.hand_type);
+            let shares = split_pot(
+                pot.amount,
+                &winners,
+                self.rules.odd_chip_rule,
+                self.players.len(),
+                (dealer + 1) % self.players.len(),
+            );
+            for (winner, share) in shares {
+                winnings[winner] += share;
             }
         }
-        drop(state);
+        // cards that time travelled stay in the deck, so the new hand
+        // keeps the record of them
+        let mut deck: Vec<Card> = state
+            .cards()
+            .map(|&card| Card {
+                origin: None,
+                ..card
+            })
+            .collect();
+        let arrivals = state.arrivals.clone();
+        let departures = state.departures.clone();
         for (i, delta) in winnings.into_iter().enumerate() {
-            self.players[i].chips += delta as i64;
+            self.players[i].chips += delta;
         }
-        deck.shuffle(&mut thread_rng());
-        self.timelines[timeline]
-            .boards
-            .push(Board::new(deck, self.players.len()));
-        winning_hand
+        self.check_chip_total();
+        deck.shuffle(&mut self.action_rng);
+        let dealer = (dealer + 1) % self.players.len();
+        // the chips on this board are settled, so they are free to bet again
+        let stacks: Vec<i64> = committed
+            .iter()
+            .enumerate()
+            .map(|(player, chips)| self.available_chips(player) + chips)
+            .collect();
+        let boards = &mut self.timelines[timeline].boards;
+        // the new hand has been in play for as long as the one that ended,
+        // so it doesn't hold back the turn
+        let elapsed = boards.last().unwrap().0.len();
+        let mut board = Board::new(deck, &stacks, dealer, &self.rules);
+        board.0[0].arrivals = arrivals;
+        board.0[0].departures = departures;
+        board.0.resize(elapsed, board.0[0].clone());
+        boards.push(board);
+        winning_hand.unwrap()
+    }
+
+    /// Burns chips for time travel, taking them out of the game
+    fn burn_chips(&mut self, player: usize, amount: i64) {
+        self.players[player].chips -= amount;
+        self.burned_chips += amount;
+        self.check_chip_total();
+    }
+
+    /// Every board holds one deck's worth of cards, apart from the ones time
+    /// travel has moved between boards
+    fn check_cards(&self) {
+        debug_assert!(
+            self.timelines
+                .iter()
+                .flat_map(|timeline| &timeline.boards)
+                .all(|board| board.get_turn(None).cards_add_up()),
+            "cards have appeared or disappeared"
+        );
+    }
+
+    /// Chips only ever change hands, apart from the ones burned on time
+    /// travel
+    fn check_chip_total(&self) {
+        let total: i64 = self.players.iter().map(|p| p.chips).sum();
+        debug_assert_eq!(
+            total + self.burned_chips,
+            self.players.len() as i64 * self.rules.starting_stack,
+            "chips were created or destroyed"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(num_players: usize, rules: GameRules) -> Multiverse {
+        let names = (0..num_players).map(|i| format!("Player {}", i + 1));
+        Multiverse::from_seed(names.collect(), rules, 7)
+    }
+
+    /// Heads up, calls and checks both timelines to the flop
+    fn to_the_flop(game: &mut Multiverse) {
+        for action in [
+            Action::Call { timeline: 0 },
+            Action::Call { timeline: 1 },
+            Action::Check { timeline: 0 },
+            Action::Check { timeline: 1 },
+        ] {
+            game.apply(action).unwrap();
+        }
+    }
+
+    #[test]
+    fn bets_open_and_raises_follow() {
+        let mut game = game(2, GameRules::default());
+        // the big blind is the bet before the flop
+        assert_eq!(
+            game.apply(Action::Bet {
+                timeline: 0,
+                amount: 4
+            }),
+            Err(RuleError::AlreadyBet)
+        );
+        to_the_flop(&mut game);
+        assert_eq!(game.current_turn(0).open_cards.len(), 3);
+        assert_eq!(
+            game.apply(Action::Raise {
+                timeline: 0,
+                amount: 4
+            }),
+            Err(RuleError::NothingToRaise)
+        );
+        assert_eq!(
+            game.apply(Action::Bet {
+                timeline: 0,
+                amount: 4
+            }),
+            Ok(Event::Bet { amount: 4 })
+        );
+    }
+
+    #[test]
+    fn bets_are_checked_against_the_rules() {
+        let mut game = game(2, GameRules::default());
+        to_the_flop(&mut game);
+        let player = game.get_active_player();
+        let bet = |amount| Action::Bet {
+            timeline: 0,
+            amount,
+        };
+        assert_eq!(
+            game.apply(bet(1)),
+            Err(RuleError::BetTooSmall { minimum: 2 })
+        );
+        assert_eq!(
+            game.apply(bet(100)),
+            Err(RuleError::InsufficientChips {
+                available: game.available_chips(player)
+            })
+        );
+        game.apply(bet(6)).unwrap();
+        game.apply(Action::Check { timeline: 1 }).unwrap();
+        assert_eq!(game.get_active_player(), 1 - player);
+        assert_eq!(
+            game.apply(Action::Check { timeline: 0 }),
+            Err(RuleError::BetToCall { amount: 6 })
+        );
+        assert_eq!(
+            game.apply(Action::Raise {
+                timeline: 0,
+                amount: 8
+            }),
+            Err(RuleError::BetTooSmall { minimum: 12 })
+        );
+        assert_eq!(game.apply(Action::Call { timeline: 0 }), Ok(Event::Called));
+        // the call closes the betting, and the turn card is dealt
+        let turn = game.timelines[0].current_board().get_turn(None);
+        assert_eq!(turn.open_cards.len(), 4);
+    }
+
+    #[test]
+    fn pot_limit_caps_bets_at_the_pot() {
+        let rules = GameRules {
+            betting: BettingStructure::PotLimit,
+            ..GameRules::default()
+        };
+        let mut game = game(2, rules);
+        to_the_flop(&mut game);
+        assert_eq!(game.bet_range(0), (2, 4));
+        assert_eq!(
+            game.apply(Action::Bet {
+                timeline: 0,
+                amount: 5
+            }),
+            Err(RuleError::BetTooLarge { maximum: 4 })
+        );
+    }
+
+    /// Plays a hand of five card draw on timeline 0 up to the draw, with
+    /// the first player to act all in, and has everyone in the hand draw
+    /// two cards. Timeline 1 is checked, called or folded along the way.
+    fn draw_with_all_in_player(num_players: usize) -> Multiverse {
+        let variant = Variant::FiveCardDraw;
+        let mut game = game(
+            num_players,
+            GameRules {
+                variant,
+                hole_cards: variant.hole_cards(),
+                ..GameRules::default()
+            },
+        );
+        let all_in = game.get_active_player();
+        game.apply(Action::AllIn { timeline: 0 }).unwrap();
+        assert!(game.current_turn(0).player_states[all_in].all_in);
+        for _ in 0..40 {
+            let first = &game.timelines[0].boards[0];
+            if first.get_turn(None).street > 1 {
+                break;
+            }
+            let moved = [
+                Action::Draw {
+                    timeline: 0,
+                    discards: vec![0, 1],
+                },
+                Action::Call { timeline: 0 },
+                Action::AllIn { timeline: 0 },
+                Action::Check { timeline: 1 },
+                Action::Call { timeline: 1 },
+                Action::Fold { timeline: 1 },
+            ]
+            .into_iter()
+            .any(|action| game.apply(action).is_ok());
+            assert!(moved);
+        }
+        game
+    }
+
+    #[test]
+    fn all_in_players_still_draw() {
+        for num_players in [2, 3] {
+            let game = draw_with_all_in_player(num_players);
+            let hand = game.timelines[0].boards[0].get_turn(None);
+            assert_eq!(hand.discards.len(), 2 * num_players);
+            assert!(hand.player_states.iter().all(|p| p.hand.len() == 5));
+        }
+    }
+
+    fn board(timeline: usize, board: usize) -> BoardCoordinate {
+        BoardCoordinate { timeline, board }
+    }
+
+    #[test]
+    fn a_short_deck_deals_what_is_left() {
+        let rules = GameRules {
+            variant: Variant::FiveCardDraw,
+            hole_cards: 26,
+            ..GameRules::default()
+        };
+        assert!(rules.is_playable(2));
+        let mut game = game(2, rules);
+        let player = game.get_active_player();
+        game.apply(Action::TimeTravel {
+            from: board(0, 0),
+            to: board(1, 0),
+            card: hole_card(player),
+            raise: 4,
+        })
+        .unwrap();
+        // the next hand on timeline 0 is one card short
+        while game.timelines[0].boards.len() == 1 {
+            let moved = [
+                Action::Fold { timeline: 0 },
+                Action::Call { timeline: 1 },
+                Action::Check { timeline: 1 },
+            ]
+            .into_iter()
+            .any(|action| game.apply(action).is_ok());
+            assert!(moved);
+        }
+        let hand = game.timelines[0].current_board().get_turn(None);
+        let mut sizes: Vec<usize> =
+            hand.player_states.iter().map(|p| p.hand.len()).collect();
+        sizes.sort();
+        assert_eq!(sizes, [25, 26]);
+        assert!(hand.deck.is_empty());
+    }
+
+    fn hole_card(player: usize) -> CardLocation {
+        CardLocation {
+            player: Some(player),
+            index: 0,
+        }
+    }
+
+    #[test]
+    fn time_travel_cost() {
+        let game = game(2, GameRules::default());
+        let player = game.get_active_player();
+        let open = CardLocation {
+            player: None,
+            index: 0,
+        };
+        assert_eq!(
+            game.time_travel_cost(board(0, 0), board(1, 0), hole_card(player)),
+            Ok(1)
+        );
+        assert_eq!(
+            game.time_travel_cost(board(0, 0), board(0, 0), hole_card(player)),
+            Err(RuleError::SameBoard)
+        );
+        assert_eq!(
+            game.time_travel_cost(board(0, 0), board(2, 0), hole_card(player)),
+            Err(RuleError::InvalidTimeline { timeline: 2 })
+        );
+        // nothing has been dealt to the board yet
+        assert_eq!(
+            game.time_travel_cost(board(0, 0), board(1, 0), open),
+            Err(RuleError::CardMissing)
+        );
+        assert_eq!(
+            game.time_travel_cost(
+                board(0, 0),
+                board(1, 0),
+                hole_card(1 - player)
+            ),
+            Err(RuleError::NotYourCard)
+        );
+    }
+
+    #[test]
+    fn time_travel_moves_the_card() {
+        let mut game = game(2, GameRules::default());
+        let player = game.get_active_player();
+        let card = game.current_turn(0).player_states[player].hand[0];
+        let chips = game.players[player].chips;
+        let event = game.apply(Action::TimeTravel {
+            from: board(0, 0),
+            to: board(1, 0),
+            card: hole_card(player),
+            raise: 4,
+        });
+        assert_eq!(
+            event,
+            Ok(Event::TimeTravelled {
+                burned: 1,
+                timeline: 1,
+                board: 0
+            })
+        );
+        assert_eq!(game.players[player].chips, chips - 1);
+        assert_eq!(game.burned_chips, 1);
+        let source = game.timelines[0].current_board().get_turn(None);
+        assert!(!source.player_states[player].hand.contains(&card));
+        assert_eq!(source.player_states[player].round_bet, 4);
+        assert!(source.departures.contains(&card));
+        let target = game.timelines[1].current_board().get_turn(None);
+        let arrived = *target.player_states[player].hand.last().unwrap();
+        assert!(arrived.same_face(&card));
+        assert_eq!(
+            arrived.origin,
+            Some(Provenance {
+                timeline: 0,
+                time: 0,
+                owner: Some(player)
+            })
+        );
+    }
+
+    #[test]
+    fn time_travel_into_the_past_branches() {
+        let mut game = game(2, GameRules::default());
+        to_the_flop(&mut game);
+        // a hand ends on timeline 0, so its first board is in the past
+        let player = game.get_active_player();
+        game.apply(Action::Fold { timeline: 0 }).unwrap();
+        let player = if game.get_active_player() == player {
+            player
+        } else {
+            game.apply(Action::Check { timeline: 1 }).unwrap();
+            game.get_active_player()
+        };
+        assert_eq!(game.timelines[0].boards.len(), 2);
+        let card = game.timelines[1]
+            .current_board()
+            .get_turn(None)
+            .player_states[player]
+            .hand[0];
+        let event = game
+            .apply(Action::TimeTravel {
+                from: board(1, 0),
+                to: board(0, 0),
+                card: hole_card(player),
+                raise: 2,
+            })
+            .unwrap();
+        assert_eq!(
+            event,
+            Event::TimeTravelled {
+                burned: 1,
+                timeline: 2,
+                board: 0
+            }
+        );
+        assert_eq!(game.timelines[2].parent_index, 0);
+        let branch = game.timelines[2].current_board().get_turn(None);
+        assert!(branch.player_states[player]
+            .hand
+            .iter()
+            .any(|arrived| arrived.same_face(&card)));
+        // the original timeline's history is left alone
+        assert!(game.timelines[0].boards[0]
+            .0
+            .iter()
+            .all(|turn| turn.arrivals.is_empty()));
+    }
+
+    #[test]
+    fn time_travel_raise_that_ends_the_hand() {
+        let mut game = game(2, GameRules::default());
+        to_the_flop(&mut game);
+        let player = game.get_active_player();
+        // with the other player all in, the raise closes the betting and the
+        // hand is dealt out
+        let turn = game.timelines[0].current_board_mut().0.last_mut().unwrap();
+        turn.player_states[1 - player].all_in = true;
+        let card = turn.player_states[player].hand[0];
+        game.apply(Action::TimeTravel {
+            from: board(0, 0),
+            to: board(1, 0),
+            card: hole_card(player),
+            raise: 2,
+        })
+        .unwrap();
+        let boards = &game.timelines[0].boards;
+        assert_eq!(boards.len(), 2);
+        let finished = boards[0].get_turn(None);
+        assert!(finished.is_finished());
+        assert!(!finished.player_states[player].hand.contains(&card));
+        let next = boards[1].get_turn(None);
+        assert!(!next.cards().any(|other| other.same_face(&card)));
+        assert!(next.departures.contains(&card));
+        let target = game.timelines[1].current_board().get_turn(None);
+        assert_eq!(
+            target
+                .cards_in_play()
+                .filter(|other| other.same_face(&card))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn all_in_on_one_timeline_is_all_in_on_all() {
+        let mut game = game(2, GameRules::default());
+        let player = game.get_active_player();
+        game.apply(Action::AllIn { timeline: 0 }).unwrap();
+        assert_eq!(game.available_chips(player), 0);
+        // nothing left to call the big blind with on the other timeline
+        let other = game.current_turn(1);
+        assert!(other.player_states[player].all_in);
+        assert_eq!(other.to_act, 1 - player);
+        assert_eq!(game.get_active_player(), 1 - player);
+        game.apply(Action::Check { timeline: 1 }).unwrap();
+        assert_eq!(game.timelines[1].boards.len(), 2);
+        game.apply(Action::AllIn { timeline: 0 }).unwrap();
+        assert_eq!(game.timelines[0].boards.len(), 2);
+        assert!(game.players.iter().all(|player| player.chips >= 0));
+    }
+
+    #[test]
+    fn forced_bets_are_capped_at_the_stack() {
+        let rules = GameRules {
+            starting_stack: 3,
+            ante: 1,
+            ..GameRules::default()
+        };
+        let game = game(3, rules);
+        // everyone antes 1, and the big blind only has 2 left of their 2
+        let first = game.current_turn(0);
+        let big_blind = (first.dealer + 2) % 3;
+        assert_eq!(first.player_states[big_blind].commitment(), 3);
+        assert!(first.player_states[big_blind].all_in);
+        // on the second timeline the big blind has nothing left to post,
+        // so they sit the hand out
+        let second = game.current_turn(1);
+        assert!(second.player_states[big_blind].folded);
+        assert!(second.player_states[big_blind].hand.is_empty());
+        assert!(second.player_states[big_blind].bet.is_empty());
+        assert!((0..3).all(|player| game.available_chips(player) >= 0));
+    }
+
+    /// Moves a random player could try on any board
+    fn random_action(game: &Multiverse, rng: &mut impl Rng) -> Action {
+        let timeline = rng.gen_range(0..game.timelines.len());
+        let amount = rng.gen_range(1..=game.rules.starting_stack);
+        match rng.gen_range(0..9) {
+            0 => Action::Check { timeline },
+            1 => Action::Call { timeline },
+            2 => Action::Bet { timeline, amount },
+            3 => Action::Raise { timeline, amount },
+            4 => Action::AllIn { timeline },
+            5 => Action::Fold { timeline },
+            6 => Action::Draw {
+                timeline,
+                discards: vec![0],
+            },
+            _ => {
+                let to = rng.gen_range(0..game.timelines.len());
+                let player = game.get_active_player();
+                Action::TimeTravel {
+                    from: board(
+                        timeline,
+                        game.timelines[timeline].boards.len() - 1,
+                    ),
+                    to: board(
+                        to,
+                        rng.gen_range(0..game.timelines[to].boards.len()),
+                    ),
+                    card: CardLocation {
+                        player: if rng.gen() { Some(player) } else { None },
+                        index: rng.gen_range(0..2),
+                    },
+                    raise: rng.gen_range(0..=4),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn random_games_never_go_below_zero_chips() {
+        for seed in 0..12 {
+            let mut rng = SplitMix64::new(seed);
+            let variant = [
+                Variant::HoldEm,
+                Variant::Omaha,
+                Variant::FiveCardDraw,
+                Variant::SevenCardStud,
+            ][seed as usize % 4];
+            let rules = GameRules {
+                starting_stack: rng.gen_range(3..=20),
+                ante: rng.gen_range(0..=2),
+                variant,
+                hole_cards: variant.hole_cards(),
+                betting: [
+                    BettingStructure::NoLimit,
+                    BettingStructure::PotLimit,
+                    BettingStructure::Limit,
+                ][rng.gen_range(0..3)],
+                paradoxes: [
+                    ParadoxPolicy::Allow,
+                    ParadoxPolicy::Forbid,
+                    ParadoxPolicy::Annihilate,
+                ][rng.gen_range(0..3)],
+                ..GameRules::default()
+            };
+            let num_players = rng.gen_range(2..=4);
+            let mut game = Multiverse::from_seed(
+                (0..num_players).map(|i| format!("Player {}", i)).collect(),
+                rules,
+                seed,
+            );
+            for _ in 0..100 {
+                // give up once nothing tried is allowed, as when the game
+                // is over
+                let moved = (0..50).any(|_| {
+                    game.apply(random_action(&game, &mut rng)).is_ok()
+                });
+                for player in 0..num_players {
+                    assert!(game.players[player].chips >= 0, "seed {}", seed);
+                    assert!(game.available_chips(player) >= 0, "seed {}", seed);
+                }
+                if !moved {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn replay_rebuilds_the_game() {
+        let mut rng = SplitMix64::new(5);
+        let mut game = game(3, GameRules::default());
+        for _ in 0..60 {
+            let _ = game.apply(random_action(&game, &mut rng));
+        }
+        // enough for hands to have ended and the decks been shuffled again
+        assert!(game.timelines.iter().any(|t| t.boards.len() > 1));
+        assert_eq!(game.replay().unwrap(), game);
+    }
+
+    /// A game under an undo policy, with the first player looking at the
+    /// boards as the frontend would have them
+    fn undo_game(undo: UndoPolicy) -> Multiverse {
+        let mut game = game(
+            2,
+            GameRules {
+                undo,
+                ..GameRules::default()
+            },
+        );
+        game.active_player = game.get_active_player();
+        game
+    }
+
+    #[test]
+    fn undo_and_redo_round_trip() {
+        let mut game = undo_game(UndoPolicy::ThisTurn);
+        let before = game.clone();
+        game.apply(Action::Call { timeline: 0 }).unwrap();
+        let after = game.clone();
+        game.undo().unwrap();
+        assert_eq!(game.timelines, before.timelines);
+        assert_eq!(game.players, before.players);
+        assert!(game.log.is_empty());
+        assert_eq!(game.redo(), Ok(Event::Called));
+        assert_eq!(game, after);
+        assert_eq!(game.redo(), Err(RuleError::CannotUndo));
+    }
+
+    #[test]
+    fn a_new_move_clears_redo() {
+        let mut game = undo_game(UndoPolicy::ThisTurn);
+        game.apply(Action::Call { timeline: 0 }).unwrap();
+        game.undo().unwrap();
+        game.apply(Action::Fold { timeline: 0 }).unwrap();
+        assert_eq!(game.redo(), Err(RuleError::CannotUndo));
+    }
+
+    #[test]
+    fn undo_never() {
+        let mut game = undo_game(UndoPolicy::Never);
+        game.apply(Action::Call { timeline: 0 }).unwrap();
+        assert!(!game.can_undo());
+        assert_eq!(game.undo(), Err(RuleError::CannotUndo));
+    }
+
+    #[test]
+    fn undo_this_turn() {
+        let mut game = undo_game(UndoPolicy::ThisTurn);
+        assert_eq!(game.undo(), Err(RuleError::CannotUndo));
+        game.apply(Action::Call { timeline: 0 }).unwrap();
+        game.apply(Action::Call { timeline: 1 }).unwrap();
+        assert!(game.can_undo());
+        // the next player takes over the boards
+        game.active_player = game.get_active_player();
+        assert!(!game.can_undo());
+        assert_eq!(game.undo(), Err(RuleError::CannotUndo));
+    }
+
+    #[test]
+    fn undo_always() {
+        let mut game = undo_game(UndoPolicy::Always);
+        game.apply(Action::Call { timeline: 0 }).unwrap();
+        game.apply(Action::Call { timeline: 1 }).unwrap();
+        game.active_player = game.get_active_player();
+        game.undo().unwrap();
+        game.undo().unwrap();
+        assert!(game.log.is_empty());
+        assert!(!game.can_undo());
+    }
+
+    /// Puts the card with the face of `face` into `player`'s hand on the
+    /// board in play on a timeline, swapping it with their first card
+    fn give_card(
+        game: &mut Multiverse,
+        timeline: usize,
+        player: usize,
+        face: Card,
+    ) {
+        let board = game.timelines[timeline].current_board_mut();
+        let turn = board.0.last_mut().unwrap();
+        let first = turn.player_states[player].hand[0];
+        let swap = |card: &mut Card| {
+            if card.same_face(&face) {
+                *card = first;
+            } else if card.same_face(&first) {
+                *card = face;
+            }
+        };
+        turn.player_states
+            .iter_mut()
+            .flat_map(|p| p.hand.iter_mut().chain(&mut p.up_cards))
+            .chain(&mut turn.open_cards)
+            .chain(&mut turn.deck)
+            .for_each(swap);
+    }
+
+    fn paradox_game(paradoxes: ParadoxPolicy) -> (Multiverse, Action, Card) {
+        let mut game = game(
+            2,
+            GameRules {
+                paradoxes,
+                ..GameRules::default()
+            },
+        );
+        let player = game.get_active_player();
+        let card = game.current_turn(0).player_states[player].hand[0];
+        // the other player holds the same card on timeline 1
+        give_card(&mut game, 1, 1 - player, card);
+        let action = Action::TimeTravel {
+            from: board(0, 0),
+            to: board(1, 0),
+            card: hole_card(player),
+            raise: 4,
+        };
+        (game, action, card)
+    }
+
+    #[test]
+    fn forbid_refuses_a_card_already_in_play() {
+        let (mut game, action, _) = paradox_game(ParadoxPolicy::Forbid);
+        assert_eq!(game.apply(action), Err(RuleError::Paradox));
+    }
+
+    #[test]
+    fn annihilate_destroys_both_copies() {
+        let (mut game, action, card) = paradox_game(ParadoxPolicy::Annihilate);
+        game.apply(action).unwrap();
+        let turn = game.timelines[1].current_board().get_turn(None);
+        assert!(!turn.cards_in_play().any(|other| other.same_face(&card)));
+        let copies = turn.annihilated.iter().filter(|a| a.same_face(&card));
+        assert_eq!(copies.count(), 2);
+    }
+
+    #[test]
+    fn forbid_keeps_the_copy_dealt_on_the_board() {
+        let game = game(2, GameRules::default());
+        let mut turn = game.current_turn(0).clone();
+        // the board deals itself a card that has already travelled here
+        let native = turn.deck.pop().unwrap();
+        let travelled = Card {
+            origin: Some(Provenance {
+                timeline: 1,
+                time: 0,
+                owner: Some(0),
+            }),
+            ..native
+        };
+        turn.player_states[0].hand.push(travelled);
+        turn.open_cards.push(native);
+        turn.resolve_paradoxes(ParadoxPolicy::Forbid);
+        assert_eq!(turn.open_cards, [native]);
+        assert!(!turn.player_states[0].hand.contains(&travelled));
+        assert_eq!(turn.annihilated, [travelled]);
+    }
+
+    #[test]
+    fn allow_plays_both_copies() {
+        let deck = crate::cards::fresh_deck();
+        let rank = deck[0].rank;
+        let quads: Vec<Card> = deck
+            .iter()
+            .filter(|card| card.rank == rank)
+            .copied()
+            .collect();
+        let travelled = Card {
+            origin: Some(Provenance {
+                timeline: 1,
+                time: 0,
+                owner: Some(0),
+            }),
+            ..quads[0]
+        };
+        let game = game(2, GameRules::default());
+        let mut turn = game.current_turn(0).clone();
+        turn.player_states[0].hand = vec![quads[0], quads[1], travelled];
+        turn.open_cards = quads[2..].to_vec();
+        turn.resolve_paradoxes(ParadoxPolicy::Allow);
+        assert_eq!(turn.paradoxes(), [travelled]);
+        let value = Variant::HoldEm
+            .hand_value(&turn.player_states[0].hand, &turn.open_cards);
+        assert_eq!(value.hand_type, crate::cards::HandType::FiveOfAKind);
+    }
+
+    #[test]
+    fn burn_cards_burns_before_each_street() {
+        let mut game = game(
+            2,
+            GameRules {
+                burn_cards: true,
+                ..GameRules::default()
+            },
+        );
+        while game.timelines[0].boards.len() == 1 {
+            let moved = [
+                Action::Call { timeline: 0 },
+                Action::Check { timeline: 0 },
+                Action::Call { timeline: 1 },
+                Action::Check { timeline: 1 },
+            ]
+            .into_iter()
+            .any(|action| game.apply(action).is_ok());
+            assert!(moved);
+        }
+        let hand = game.timelines[0].boards[0].get_turn(None);
+        assert!(hand.is_finished());
+        assert_eq!(hand.open_cards.len(), 5);
+        assert_eq!(hand.burned.len(), 3);
+    }
+
+    #[test]
+    fn time_travel_cost_refuses_moves_that_would_fail() {
+        let mut game = game(2, GameRules::default());
+        to_the_flop(&mut game);
+        // the hand on timeline 0 ends, and the next one starts a new board
+        game.apply(Action::Fold { timeline: 0 }).unwrap();
+        game.apply(Action::Check { timeline: 1 }).unwrap();
+        let player = game.get_active_player();
+        assert_eq!(
+            game.time_travel_cost(board(0, 0), board(1, 0), hole_card(player)),
+            Err(RuleError::InvalidTime {
+                timeline: 0,
+                board: 0
+            })
+        );
+        assert_eq!(
+            game.time_travel_cost(board(1, 0), board(0, 0), hole_card(player)),
+            Err(RuleError::BoardFinished)
+        );
+        assert_eq!(
+            game.time_travel_cost(board(1, 0), board(0, 1), hole_card(player)),
+            Ok(2)
+        );
+    }
+
+    #[test]
+    fn hands_ending_together_are_shuffled_differently() {
+        let mut game = game(2, GameRules::default());
+        // both hands hold the same cards in the same places
+        game.timelines[1] = game.timelines[0].clone();
+        game.action_rng = SplitMix64::new(1);
+        game.showdown(0);
+        game.showdown(1);
+        let deck = |timeline: usize| &game.timelines[timeline].boards[1].0[0];
+        assert_ne!(deck(0).deck, deck(1).deck);
     }
 }
@@ -1,4 +1,6 @@
 use crate::multiverse::*;
+use crate::rules::*;
+use crate::storage::*;
 use wasm_bindgen::JsCast;
 use web_sys::*;
 use yew::prelude::*;
@@ -28,6 +30,45 @@ pub fn NewGame(props: &NewGameProps) -> Html {
             </div>
         }
     });
+    let defaults = GameRules::default();
+    let rule_inputs = [
+        ("StartingStack", "Stack", Some(defaults.starting_stack)),
+        ("Ante", "Ante", Some(defaults.ante)),
+        ("SmallBlind", "Small blind", Some(defaults.small_blind)),
+        ("BigBlind", "Big blind", Some(defaults.big_blind)),
+        // left empty, so that it follows the variant
+        ("HoleCards", "Hole cards", None),
+        ("TimeCost", "⏲ per turn", Some(defaults.time_cost)),
+        ("TimelineCost", "⏲ per timeline", Some(defaults.timeline_cost)),
+        ("OpenCardCost", "⏲ open card", Some(defaults.open_card_cost)),
+    ]
+    .into_iter()
+    .map(|(id, label, value)| {
+        let value = value.map(|v| v.to_string()).unwrap_or_default();
+        html! {
+            <label>
+                {label}
+                <input id={id} type="number" min="0" placeholder="auto" {value} />
+            </label>
+        }
+    });
+    let resume_buttons = save_slots().into_iter().map(|slot| {
+        let label = if slot == AUTOSAVE {
+            "Resume last game".to_string()
+        } else {
+            format!("Resume {}", slot)
+        };
+        let onclick = {
+            let ongamecreate = props.ongamecreate.clone();
+            move |_e: MouseEvent| match load_game(&slot) {
+                Some(game) => ongamecreate.emit(game),
+                None => gloo_dialogs::alert("That game can't be loaded"),
+            }
+        };
+        html! {
+            <button class="do-button" {onclick}>{label}</button>
+        }
+    });
     let onclick = {
         let ongamecreate = props.ongamecreate.clone();
         move |_e: MouseEvent| {
@@ -35,6 +76,69 @@ pub fn NewGame(props: &NewGameProps) -> Html {
                 .expect("no window")
                 .document()
                 .expect("no document");
+            let number = |id: &str, default: i64| {
+                document
+                    .get_element_by_id(id)
+                    .unwrap()
+                    .unchecked_into::<HtmlInputElement>()
+                    .value()
+                    .parse()
+                    .unwrap_or(default)
+            };
+            let defaults = GameRules::default();
+            let choice = |id: &str| {
+                document
+                    .get_element_by_id(id)
+                    .unwrap()
+                    .unchecked_into::<HtmlSelectElement>()
+                    .value()
+            };
+            let variant = match choice("Variant").as_str() {
+                "omaha" => Variant::Omaha,
+                "draw" => Variant::FiveCardDraw,
+                "stud" => Variant::SevenCardStud,
+                _ => Variant::HoldEm,
+            };
+            let paradoxes = match choice("Paradoxes").as_str() {
+                "forbid" => ParadoxPolicy::Forbid,
+                "annihilate" => ParadoxPolicy::Annihilate,
+                _ => ParadoxPolicy::Allow,
+            };
+            let undo = match choice("Undo").as_str() {
+                "never" => UndoPolicy::Never,
+                "always" => UndoPolicy::Always,
+                _ => UndoPolicy::ThisTurn,
+            };
+            let betting = match choice("Betting").as_str() {
+                "pot-limit" => BettingStructure::PotLimit,
+                "limit" => BettingStructure::Limit,
+                _ => BettingStructure::NoLimit,
+            };
+            let burn_cards = document
+                .get_element_by_id("BurnCards")
+                .unwrap()
+                .unchecked_into::<HtmlInputElement>()
+                .checked();
+            let rules = GameRules {
+                starting_stack: number(
+                    "StartingStack",
+                    defaults.starting_stack,
+                ),
+                ante: number("Ante", defaults.ante),
+                small_blind: number("SmallBlind", defaults.small_blind),
+                big_blind: number("BigBlind", defaults.big_blind),
+                variant,
+                burn_cards,
+                paradoxes,
+                undo,
+                hole_cards: number("HoleCards", variant.hole_cards() as i64)
+                    .max(0) as usize,
+                betting,
+                time_cost: number("TimeCost", defaults.time_cost),
+                timeline_cost: number("TimelineCost", defaults.timeline_cost),
+                open_card_cost: number("OpenCardCost", defaults.open_card_cost),
+                ..defaults
+            };
             let players: Vec<String> = (0..=5)
                 .map(|i| {
                     document
@@ -44,11 +148,31 @@ pub fn NewGame(props: &NewGameProps) -> Html {
                         .value()
                         .to_string()
                 })
-                .filter(|a| a.len() != 0)
+                .filter(|a| !a.is_empty())
                 .collect();
-            if players.len() >= 2 {
-                ongamecreate.emit(Multiverse::from_players(players));
+            if players.len() < 2 {
+                return;
+            }
+            if !rules.is_playable(players.len()) {
+                gloo_dialogs::alert(
+                    "The game can't be played with these rules",
+                );
+                return;
             }
+            let seed = document
+                .get_element_by_id("Seed")
+                .unwrap()
+                .unchecked_into::<HtmlInputElement>()
+                .value();
+            let game = if seed.trim().is_empty() {
+                Multiverse::from_players(players, rules)
+            } else if let Ok(seed) = seed.trim().parse() {
+                Multiverse::from_seed(players, rules, seed)
+            } else {
+                gloo_dialogs::alert("The seed has to be a whole number");
+                return;
+            };
+            ongamecreate.emit(game);
         }
     };
     html! {
@@ -58,7 +182,54 @@ pub fn NewGame(props: &NewGameProps) -> Html {
         <div id="title3">{"Poker"}</div>
         <div class="table centered">
             {for inputs}
+            <div class="rules">
+                {for rule_inputs}
+                <label>
+                    {"Game"}
+                    <select id="Variant">
+                        <option value="holdem">{"Hold'em"}</option>
+                        <option value="omaha">{"Omaha"}</option>
+                        <option value="draw">{"Five-card draw"}</option>
+                        <option value="stud">{"Seven-card stud"}</option>
+                    </select>
+                </label>
+                <label>
+                    {"Betting"}
+                    <select id="Betting">
+                        <option value="no-limit">{"No limit"}</option>
+                        <option value="pot-limit">{"Pot limit"}</option>
+                        <option value="limit">{"Limit"}</option>
+                    </select>
+                </label>
+                <label>
+                    {"Paradoxes"}
+                    <select id="Paradoxes">
+                        <option value="allow">{"Allowed"}</option>
+                        <option value="forbid">{"Forbidden"}</option>
+                        <option value="annihilate">{"Annihilate"}</option>
+                    </select>
+                </label>
+                <label>
+                    {"Undo"}
+                    <select id="Undo">
+                        <option value="this-turn">{"Until next turn"}</option>
+                        <option value="always">{"Always"}</option>
+                        <option value="never">{"Never"}</option>
+                    </select>
+                </label>
+                <label>
+                    {"Burn cards"}
+                    <input id="BurnCards" type="checkbox" />
+                </label>
+                <label>
+                    {"Seed"}
+                    <input id="Seed" class="seed" placeholder="random" />
+                </label>
+            </div>
             <button class="start-game do-button" {onclick}>{"Start"}</button>
+            <div class="saves">
+                {for resume_buttons}
+            </div>
         </div>
         </>
     }
@@ -1,28 +1,32 @@
 use crate::cards::Card;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Player {
     pub name: String,
     pub chips: i64,
 }
 
 impl Player {
-    pub fn from_name(name: String) -> Self {
-        Self { name, chips: 30 }
+    pub fn new(name: String, chips: i64) -> Self {
+        Self { name, chips }
     }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct PlayerState {
     pub hand: Vec<Card>,
+    /// cards dealt face up, which everyone can see
+    pub up_cards: Vec<Card>,
     pub bet: Vec<i64>,
     pub folded: bool,
+    pub all_in: bool,
+    /// chips put in on the current street, blinds included
+    pub round_bet: i64,
 }
 
 impl PlayerState {
     pub fn commitment(&self) -> i64 {
-        self.bet.iter().sum::<i64>() + Self::ANTE
+        self.bet.iter().sum::<i64>()
     }
-
-    const ANTE: i64 = 1;
 }
@@ -0,0 +1,175 @@
+use crate::player::PlayerState;
+use serde::{Deserialize, Serialize};
+
+/// A share of the chips on a board, which can only be won by the players
+/// that put in enough to contest it
+#[derive(Clone, PartialEq, Debug)]
+pub struct Pot {
+    pub amount: i64,
+    pub eligible: Vec<usize>,
+}
+
+/// Splits everything committed on a board into the main pot followed by any
+/// side pots. A new pot starts at each level a player went all in for, and
+/// chips left behind by folded players go into the pots they reached.
+pub fn build_pots(player_states: &[PlayerState]) -> Vec<Pot> {
+    let mut levels: Vec<i64> = player_states
+        .iter()
+        .filter(|p| !p.folded)
+        .map(|p| p.commitment())
+        .collect();
+    levels.sort();
+    levels.dedup();
+
+    let mut pots = vec![];
+    let mut previous = 0;
+    for (i, &level) in levels.iter().enumerate() {
+        // anything above the highest contested level can only go to the
+        // last pot
+        let top = if i + 1 == levels.len() {
+            i64::MAX
+        } else {
+            level
+        };
+        let amount = player_states
+            .iter()
+            .map(|p| p.commitment().min(top) - p.commitment().min(previous))
+            .sum();
+        let eligible = (0..player_states.len())
+            .filter(|&i| {
+                !player_states[i].folded
+                    && player_states[i].commitment() >= level
+            })
+            .collect();
+        pots.push(Pot { amount, eligible });
+        previous = level;
+    }
+    pots
+}
+
+/// Decides who gets the chips left over when a pot can't be split evenly
+#[derive(
+    Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize,
+)]
+pub enum OddChipRule {
+    /// the first winner in seat order gets the first odd chip
+    #[default]
+    FirstSeat,
+    /// the last winner in seat order gets the first odd chip
+    LastSeat,
+}
+
+/// Divides a pot between its winners in whole chips. Whatever doesn't divide
+/// evenly is handed out one chip at a time, in seat order counted from
+/// `first_seat` and following `rule`.
+pub fn split_pot(
+    amount: i64,
+    winners: &[usize],
+    rule: OddChipRule,
+    num_seats: usize,
+    first_seat: usize,
+) -> Vec<(usize, i64)> {
+    let mut order = winners.to_vec();
+    order.sort_by_key(|&seat| (seat + num_seats - first_seat) % num_seats);
+    if rule == OddChipRule::LastSeat {
+        order.reverse();
+    }
+    let share = amount / winners.len() as i64;
+    let odd_chips = (amount % winners.len() as i64) as usize;
+    order
+        .into_iter()
+        .enumerate()
+        .map(|(i, seat)| (seat, share + if i < odd_chips { 1 } else { 0 }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(bet: i64, folded: bool) -> PlayerState {
+        PlayerState {
+            hand: vec![],
+            up_cards: vec![],
+            bet: vec![bet],
+            folded,
+            all_in: false,
+            round_bet: 0,
+        }
+    }
+
+    #[test]
+    fn short_all_in_makes_a_side_pot() {
+        let states = [state(5, false), state(10, false), state(10, false)];
+        assert_eq!(
+            build_pots(&states),
+            [
+                Pot {
+                    amount: 15,
+                    eligible: vec![0, 1, 2]
+                },
+                Pot {
+                    amount: 10,
+                    eligible: vec![1, 2]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn folded_chips_above_the_top_level_go_to_the_last_pot() {
+        let states = [state(20, true), state(5, false), state(10, false)];
+        assert_eq!(
+            build_pots(&states),
+            [
+                Pot {
+                    amount: 15,
+                    eligible: vec![1, 2]
+                },
+                Pot {
+                    amount: 20,
+                    eligible: vec![2]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn equal_bets_make_one_pot() {
+        let states = [state(4, false), state(4, true), state(4, false)];
+        assert_eq!(
+            build_pots(&states),
+            [Pot {
+                amount: 12,
+                eligible: vec![0, 2]
+            }]
+        );
+    }
+
+    #[test]
+    fn odd_chip_goes_to_the_first_winner_left_of_the_button() {
+        // the button is on seat 0, so seat 2 comes before seat 0
+        assert_eq!(
+            split_pot(5, &[0, 2], OddChipRule::FirstSeat, 3, 1),
+            [(2, 3), (0, 2)]
+        );
+        assert_eq!(
+            split_pot(5, &[0, 2], OddChipRule::LastSeat, 3, 1),
+            [(0, 3), (2, 2)]
+        );
+    }
+
+    #[test]
+    fn shares_add_up_to_the_pot() {
+        for rule in [OddChipRule::FirstSeat, OddChipRule::LastSeat] {
+            for amount in 0..20 {
+                let shares = split_pot(amount, &[1, 2, 4], rule, 5, 3);
+                let total: i64 = shares.iter().map(|(_, share)| share).sum();
+                assert_eq!(total, amount);
+                let most = shares.iter().map(|(_, share)| share).max();
+                let least = shares.iter().map(|(_, share)| share).min();
+                assert!(most.unwrap() - least.unwrap() <= 1);
+            }
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use crate::cards::*;
+use crate::pot::OddChipRule;
+use serde::{Deserialize, Serialize};
+
+/// The settings a game is played with, chosen when it is created
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct GameRules {
+    /// chips every player starts with
+    pub starting_stack: i64,
+    /// forced bet from every player at the start of each hand
+    pub ante: i64,
+    pub small_blind: i64,
+    /// also the smallest bet, and the small bet in limit games
+    pub big_blind: i64,
+    pub variant: Variant,
+    /// cards dealt to each player at the start of a hand
+    pub hole_cards: usize,
+    pub betting: BettingStructure,
+    /// whether a card is burned before each street after the first
+    pub burn_cards: bool,
+    pub paradoxes: ParadoxPolicy,
+    pub undo: UndoPolicy,
+    pub odd_chip_rule: OddChipRule,
+    /// chips burned for every step a card travels through time
+    pub time_cost: i64,
+    /// chips burned for every timeline a card crosses
+    pub timeline_cost: i64,
+    /// extra chips burned for moving an open card
+    pub open_card_cost: i64,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self {
+            starting_stack: 30,
+            ante: 0,
+            small_blind: 1,
+            big_blind: 2,
+            variant: Variant::default(),
+            hole_cards: Variant::default().hole_cards(),
+            betting: BettingStructure::default(),
+            burn_cards: false,
+            paradoxes: ParadoxPolicy::default(),
+            undo: UndoPolicy::default(),
+            odd_chip_rule: OddChipRule::default(),
+            time_cost: 1,
+            timeline_cost: 1,
+            open_card_cost: 4,
+        }
+    }
+}
+
+impl GameRules {
+    /// Whether a game for `num_players` can be played with these rules:
+    /// the amounts make sense, and the deck is big enough to deal everyone
+    /// their cards and the board
+    pub fn is_playable(&self, num_players: usize) -> bool {
+        let streets = self.variant.streets();
+        let per_player = self.hole_cards
+            + streets
+                .iter()
+                .map(|street| match street {
+                    Street::Deal { down, up } => down + up,
+                    _ => 0,
+                })
+                .sum::<usize>();
+        // the board, and a burn card before every street dealt after the
+        // first
+        let community: usize = streets
+            .iter()
+            .enumerate()
+            .map(|(i, street)| {
+                let burn = self.burn_cards && i > 0;
+                match street {
+                    Street::Community(n) => n + burn as usize,
+                    Street::Deal { .. } => burn as usize,
+                    _ => 0,
+                }
+            })
+            .sum();
+        self.starting_stack > 0
+            && self.ante >= 0
+            && 0 <= self.small_blind
+            && self.small_blind <= self.big_blind
+            && self.big_blind > 0
+            && self.hole_cards
+                >= match self.variant {
+                    Variant::Omaha => 2,
+                    _ => 1,
+                }
+            && per_player * num_players + community <= 52
+            && self.time_cost >= 0
+            && self.timeline_cost >= 0
+            && self.open_card_cost >= 0
+    }
+}
+
+/// The kind of poker being played
+#[derive(
+    Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize,
+)]
+pub enum Variant {
+    /// the best five of a player's hole cards and the board
+    #[default]
+    HoldEm,
+    /// exactly two hole cards and three from the board
+    Omaha,
+    /// five cards each, no board, and one chance to swap some of them
+    FiveCardDraw,
+    /// three to seven cards each, some of them dealt face up, and no board
+    SevenCardStud,
+}
+
+/// A step in playing out a hand, after the hole cards have been dealt and
+/// the forced bets posted
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Street {
+    /// cards dealt face up to the board
+    Community(usize),
+    /// cards dealt to every player still in the hand, face down and face up
+    Deal { down: usize, up: usize },
+    /// every player still in the hand can swap some of their cards
+    Draw,
+    /// a round of betting
+    Betting,
+}
+
+impl Variant {
+    /// How many hole cards are dealt unless the rules say otherwise
+    pub fn hole_cards(&self) -> usize {
+        match self {
+            Variant::HoldEm => 2,
+            Variant::Omaha => 4,
+            Variant::FiveCardDraw => 5,
+            Variant::SevenCardStud => 2,
+        }
+    }
+
+    /// How a hand is played out, in order. It goes to showdown after the
+    /// last street.
+    pub fn streets(&self) -> &'static [Street] {
+        use Street::*;
+        match self {
+            Variant::HoldEm | Variant::Omaha => &[
+                Betting,
+                Community(3),
+                Betting,
+                Community(1),
+                Betting,
+                Community(1),
+                Betting,
+            ],
+            Variant::FiveCardDraw => &[Betting, Draw, Betting],
+            Variant::SevenCardStud => &[
+                Deal { down: 0, up: 1 },
+                Betting,
+                Deal { down: 0, up: 1 },
+                Betting,
+                Deal { down: 0, up: 1 },
+                Betting,
+                Deal { down: 0, up: 1 },
+                Betting,
+                Deal { down: 1, up: 0 },
+                Betting,
+            ],
+        }
+    }
+
+    /// How many betting rounds have been completed before `street`
+    pub fn betting_round(&self, street: usize) -> usize {
+        self.streets()[..street.min(self.streets().len())]
+            .iter()
+            .filter(|&&street| street == Street::Betting)
+            .count()
+    }
+
+    /// Scores a player's hand at showdown. `hole` has all of their cards,
+    /// face up or not.
+    pub fn hand_value(&self, hole: &[Card], board: &[Card]) -> HandValue {
+        match self {
+            Variant::Omaha => omaha_hand(hole, board),
+            _ => {
+                let cards: Vec<Card> =
+                    hole.iter().chain(board).copied().collect();
+                type_of_hand(&cards)
+            }
+        }
+    }
+}
+
+/// What happens when time travel puts a card in play on a board where the
+/// same card already is
+#[derive(
+    Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize,
+)]
+pub enum ParadoxPolicy {
+    /// both copies play, so five of a kind is possible
+    #[default]
+    Allow,
+    /// a card can't be sent where it is already in play. A copy the board
+    /// deals itself later on beats the one that time travelled.
+    Forbid,
+    /// both copies are destroyed
+    Annihilate,
+}
+
+/// When a player can take back their moves
+#[derive(
+    Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize,
+)]
+pub enum UndoPolicy {
+    Never,
+    /// only until the next player starts their turn
+    #[default]
+    ThisTurn,
+    Always,
+}
+
+/// Limits on how much can be bet or raised
+#[derive(
+    Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize,
+)]
+pub enum BettingStructure {
+    /// a raise has to be at least as big as the last raise on the street,
+    /// and can go up to everything the player has
+    #[default]
+    NoLimit,
+    /// like no limit, but a raise can be at most the size of the pot after
+    /// calling
+    PotLimit,
+    /// fixed limit: bets and raises are one small bet for the first two
+    /// betting rounds (up to the turn in hold'em), and two after
+    Limit,
+}
+
+/// Why the engine refused a move
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RuleError {
+    /// bets and raises have to be at least `minimum`
+    BetTooSmall {
+        minimum: i64,
+    },
+    BetTooLarge {
+        maximum: i64,
+    },
+    /// checking isn't allowed while there is a bet to call
+    BetToCall {
+        amount: i64,
+    },
+    InvalidTimeline {
+        timeline: usize,
+    },
+    /// there is no such board on the timeline, or it can't be used for this
+    InvalidTime {
+        timeline: usize,
+        board: usize,
+    },
+    /// the card isn't (or is no longer) where the move says it is
+    CardMissing,
+    /// a card has to travel to a different board
+    SameBoard,
+    InsufficientChips {
+        available: i64,
+    },
+    /// it isn't the player's move on this board: they've already moved here
+    /// this turn, or they've folded or are all in
+    OutOfTurn,
+    /// the hand on this board has already been played out
+    BoardFinished,
+    /// the card is already in play where it would be sent, and the rules
+    /// forbid paradoxes
+    Paradox,
+    /// the move doesn't fit the street: betting while players are drawing,
+    /// or drawing while they are betting
+    WrongStreet,
+    /// there is no move to undo or redo, or the rules don't allow it
+    CannotUndo,
+    /// opening the betting when there is already a bet, which has to be
+    /// raised instead
+    AlreadyBet,
+    /// raising when there is no bet yet, which has to be opened instead
+    NothingToRaise,
+    /// only a player's own hole cards and the open cards can be moved
+    NotYourCard,
+    /// folding when nobody else is left in the hand to win it
+    LastInHand,
+}
+
+impl std::fmt::Display for RuleError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> Result<(), std::fmt::Error> {
+        match self {
+            RuleError::BetTooSmall { minimum } => {
+                write!(f, "You have to bet at least {}⏲", minimum)
+            }
+            RuleError::BetTooLarge { maximum } => {
+                write!(f, "You can bet at most {}⏲", maximum)
+            }
+            RuleError::BetToCall { amount } => {
+                write!(f, "You can't check, there is a bet of {}⏲", amount)
+            }
+            RuleError::InvalidTimeline { timeline } => {
+                write!(f, "There is no timeline {}", timeline)
+            }
+            RuleError::InvalidTime { timeline, board } => {
+                write!(
+                    f,
+                    "Board {} of timeline {} can't be used",
+                    board, timeline
+                )
+            }
+            RuleError::CardMissing => {
+                write!(f, "That card isn't there any more")
+            }
+            RuleError::SameBoard => {
+                write!(f, "The card has to travel to another board")
+            }
+            RuleError::InsufficientChips { available } => {
+                write!(f, "You only have {}⏲ left", available)
+            }
+            RuleError::OutOfTurn => {
+                write!(f, "It isn't your move on this board")
+            }
+            RuleError::BoardFinished => write!(f, "This hand is already over"),
+            RuleError::Paradox => {
+                write!(f, "That card is already in play there")
+            }
+            RuleError::WrongStreet => {
+                write!(f, "You can't do that on this street")
+            }
+            RuleError::CannotUndo => {
+                write!(f, "That move can't be taken back")
+            }
+            RuleError::AlreadyBet => {
+                write!(f, "There is already a bet, you have to raise")
+            }
+            RuleError::NothingToRaise => {
+                write!(f, "There is no bet to raise, you have to bet")
+            }
+            RuleError::NotYourCard => {
+                write!(f, "You can only move your own cards or open cards")
+            }
+            RuleError::LastInHand => {
+                write!(f, "You're the only one left in this hand")
+            }
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use crate::action::LoggedAction;
+use crate::multiverse::Multiverse;
+use crate::rules::{GameRules, RuleError};
+use serde::{Deserialize, Serialize};
+
+/// Version of the save format. Bump it whenever a change to the game state
+/// would make older saves load wrongly, so that they are turned away instead.
+pub const SAVE_VERSION: u32 = 1;
+
+/// A saved game: the whole multiverse, tagged with the format version.
+/// The version comes first, so it can be read without the rest.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub game: Multiverse,
+}
+
+/// A `SaveFile` borrowing the game, to save it without a copy
+#[derive(Serialize)]
+struct SaveRef<'a> {
+    version: u32,
+    game: &'a Multiverse,
+}
+
+/// A game saved as the moves that led to it: the players, rules and seed
+/// it started from, and the action log. It stays small however long the
+/// game goes on, where the whole state keeps every snapshot of every board.
+/// The version is checked on its own first, with `SaveHeader`.
+#[derive(Deserialize)]
+struct LogSave {
+    players: Vec<String>,
+    rules: GameRules,
+    seed: u64,
+    log: Vec<LoggedAction>,
+}
+
+/// A `LogSave` borrowing the game, tagged with the format version
+#[derive(Serialize)]
+struct LogSaveRef<'a> {
+    version: u32,
+    players: Vec<&'a str>,
+    rules: GameRules,
+    seed: u64,
+    log: &'a [LoggedAction],
+}
+
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+/// Why a saved game couldn't be loaded
+#[derive(Debug)]
+pub enum SaveError {
+    /// saved by a version of the game this one can't read
+    Version {
+        found: u32,
+    },
+    Json(serde_json::Error),
+    Binary(bincode::Error),
+    /// a logged move that the rules refuse when it is played again
+    Replay(RuleError),
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> Result<(), std::fmt::Error> {
+        match self {
+            SaveError::Version { found } => write!(
+                f,
+                "The save is from version {}, but only version {} can be loaded",
+                found, SAVE_VERSION
+            ),
+            SaveError::Json(error) => write!(f, "The save is invalid: {}", error),
+            SaveError::Binary(error) => {
+                write!(f, "The save is invalid: {}", error)
+            }
+            SaveError::Replay(error) => {
+                write!(f, "The saved moves can't be played again: {}", error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(error: serde_json::Error) -> Self {
+        SaveError::Json(error)
+    }
+}
+
+impl From<bincode::Error> for SaveError {
+    fn from(error: bincode::Error) -> Self {
+        SaveError::Binary(error)
+    }
+}
+
+impl From<RuleError> for SaveError {
+    fn from(error: RuleError) -> Self {
+        SaveError::Replay(error)
+    }
+}
+
+fn check_version(version: u32) -> Result<(), SaveError> {
+    if version == SAVE_VERSION {
+        Ok(())
+    } else {
+        Err(SaveError::Version { found: version })
+    }
+}
+
+impl Multiverse {
+    fn save_ref(&self) -> SaveRef<'_> {
+        SaveRef {
+            version: SAVE_VERSION,
+            game: self,
+        }
+    }
+
+    /// The game as readable JSON, for sharing and diffing
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.save_ref())
+            .expect("game state is always serializable")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, SaveError> {
+        let header: SaveHeader = serde_json::from_str(json)?;
+        check_version(header.version)?;
+        let save: SaveFile = serde_json::from_str(json)?;
+        Ok(save.game)
+    }
+
+    /// The game in a compact binary form
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::serialize(&self.save_ref())
+            .expect("game state is always serializable")
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SaveError> {
+        let header: SaveHeader = bincode::deserialize(bytes)?;
+        check_version(header.version)?;
+        let save: SaveFile = bincode::deserialize(bytes)?;
+        Ok(save.game)
+    }
+
+    /// The game as compact JSON of its action log, which is rebuilt by
+    /// replaying it. What the players were looking at isn't kept, nor are
+    /// the moves that were undone.
+    pub fn to_log_json(&self) -> String {
+        serde_json::to_string(&LogSaveRef {
+            version: SAVE_VERSION,
+            players: self.players.iter().map(|p| p.name.as_str()).collect(),
+            rules: self.rules,
+            seed: self.seed,
+            log: &self.log,
+        })
+        .expect("game state is always serializable")
+    }
+
+    pub fn from_log_json(json: &str) -> Result<Self, SaveError> {
+        let header: SaveHeader = serde_json::from_str(json)?;
+        check_version(header.version)?;
+        let save: LogSave = serde_json::from_str(json)?;
+        Ok(Multiverse::from_log(
+            save.players,
+            save.rules,
+            save.seed,
+            &save.log,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::*;
+    use crate::rules::GameRules;
+
+    /// A game a few moves in, with a card sent to the other timeline
+    fn game() -> Multiverse {
+        let names = vec!["Alice".to_string(), "Bob".to_string()];
+        let mut game = Multiverse::from_seed(names, GameRules::default(), 3);
+        let player = game.get_active_player();
+        game.apply(Action::TimeTravel {
+            from: BoardCoordinate {
+                timeline: 0,
+                board: 0,
+            },
+            to: BoardCoordinate {
+                timeline: 1,
+                board: 0,
+            },
+            card: CardLocation {
+                player: Some(player),
+                index: 0,
+            },
+            raise: 4,
+        })
+        .unwrap();
+        game.apply(Action::Call { timeline: 1 }).unwrap();
+        game
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let game = game();
+        assert_eq!(Multiverse::from_json(&game.to_json()).unwrap(), game);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let game = game();
+        assert_eq!(Multiverse::from_bytes(&game.to_bytes()).unwrap(), game);
+    }
+
+    #[test]
+    fn other_versions_are_turned_away() {
+        let game = game();
+        let json = game.to_json().replacen(
+            &format!("\"version\": {}", SAVE_VERSION),
+            "\"version\": 0",
+            1,
+        );
+        assert!(matches!(
+            Multiverse::from_json(&json),
+            Err(SaveError::Version { found: 0 })
+        ));
+        let mut bytes = game.to_bytes();
+        bytes[0] += 1;
+        assert!(matches!(
+            Multiverse::from_bytes(&bytes),
+            Err(SaveError::Version { found }) if found == SAVE_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn broken_saves_are_turned_away() {
+        assert!(matches!(
+            Multiverse::from_json("{\"version\": 1"),
+            Err(SaveError::Json(_))
+        ));
+        let bytes = game().to_bytes();
+        assert!(matches!(
+            Multiverse::from_bytes(&bytes[..bytes.len() / 2]),
+            Err(SaveError::Binary(_))
+        ));
+    }
+
+    #[test]
+    fn log_round_trip() {
+        let game = game();
+        let loaded = Multiverse::from_log_json(&game.to_log_json()).unwrap();
+        assert_eq!(loaded.timelines, game.timelines);
+        assert_eq!(loaded.players, game.players);
+        assert_eq!(loaded.log, game.log);
+        assert!(game.to_log_json().len() * 20 < game.to_json().len());
+    }
+
+    #[test]
+    fn logs_that_cannot_be_replayed_are_turned_away() {
+        let log = game().to_log_json();
+        // a raise of 1 is below the minimum
+        let json = log.replacen("\"raise\":4", "\"raise\":1", 1);
+        assert!(matches!(
+            Multiverse::from_log_json(&json),
+            Err(SaveError::Replay(_))
+        ));
+        let json = log.replacen(
+            &format!("\"version\":{}", SAVE_VERSION),
+            "\"version\":0",
+            1,
+        );
+        assert!(matches!(
+            Multiverse::from_log_json(&json),
+            Err(SaveError::Version { found: 0 })
+        ));
+    }
+}
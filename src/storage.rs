@@ -0,0 +1,51 @@
+use crate::multiverse::Multiverse;
+use web_sys::{window, Storage};
+
+/// Slot the game in progress is written to after every move
+pub const AUTOSAVE: &str = "autosave";
+
+/// Keys of saved games in local storage start with this, so that they can
+/// be told apart from anything else stored for the page
+const PREFIX: &str = "time-travel-poker/";
+
+fn local_storage() -> Option<Storage> {
+    window()?.local_storage().ok()?
+}
+
+/// Writes a game to a save slot, replacing what was saved there. Only the
+/// action log is stored, which keeps saves well within the storage quota.
+/// Returns whether it worked, as storage can be full or turned off.
+pub fn save_game(slot: &str, game: &Multiverse) -> bool {
+    let saved = local_storage().and_then(|storage| {
+        storage
+            .set_item(&format!("{}{}", PREFIX, slot), &game.to_log_json())
+            .ok()
+    });
+    if saved.is_none() {
+        log::warn!("couldn't save the game to slot {}", slot);
+    }
+    saved.is_some()
+}
+
+/// The game saved in a slot, if there is one that can still be loaded
+pub fn load_game(slot: &str) -> Option<Multiverse> {
+    let json = local_storage()?
+        .get_item(&format!("{}{}", PREFIX, slot))
+        .ok()??;
+    Multiverse::from_log_json(&json)
+        .map_err(|error| log::warn!("couldn't load slot {}: {}", slot, error))
+        .ok()
+}
+
+/// Names of all save slots, the autosave included
+pub fn save_slots() -> Vec<String> {
+    let Some(storage) = local_storage() else {
+        return vec![];
+    };
+    let mut slots: Vec<String> = (0..storage.length().unwrap_or(0))
+        .filter_map(|i| storage.key(i).ok()?)
+        .filter_map(|key| Some(key.strip_prefix(PREFIX)?.to_string()))
+        .collect();
+    slots.sort();
+    slots
+}